@@ -1,18 +1,25 @@
+use jiff::tz::TimeZone;
+
 use crate::cli::Cli;
+use crate::config::pricing_table::PricingTable;
 use crate::display::Display;
 
 pub struct App {
     pub cli: Cli,
     pub display: Display,
+    pub pricing: Vec<PricingTable>,
+    pub time_zone: TimeZone,
 }
 
 impl App {
-    pub fn new(cli: Cli) -> Self {
+    pub fn new(cli: Cli, pricing: Vec<PricingTable>, time_zone: TimeZone) -> Self {
         let no_animate_flag = cli.no_animate.to_owned();
 
         App {
             cli,
             display: Display::new(no_animate_flag),
+            pricing,
+            time_zone,
         }
     }
 }
@@ -1,4 +1,3 @@
 // pub mod claude;
-pub mod transformation;
 pub mod unified;
 pub mod usage_report;
@@ -45,23 +45,42 @@
 //!
 
 use itertools::Itertools;
-use std::collections::HashMap;
+use jiff::civil::Date;
+use jiff::tz::TimeZone;
+use jiff::{Span, Timestamp, Zoned};
+use std::collections::{BTreeSet, HashMap};
 
-use crate::cli::Provider;
-use crate::config::pricing_table::{PRICING, PricingTable};
+use crate::cli::{Grouping, Provider, UnknownModelPolicy};
+use crate::config::pricing_table::{ANY_CONTEXT_WINDOW, PricingTable};
 use crate::error::Error;
 use crate::io::unified_dtos::{UnifiedBucketByTime, UnifiedUsageEntry, UnifiedUsageEntryCollapsed};
 use crate::prelude::*;
 
 use super::usage_report::UsageReport;
 
+/// The values of every requested grouping dimension, in order.
+/// Example: `["default", "claude-sonnet-4-5"]` for `--group-by workspace,model`.
+pub type GroupKey = Vec<String>;
+
+/// The primitive form. Usage collapsed per provider, then per group key.
+pub type Primitives = HashMap<Provider, HashMap<GroupKey, UnifiedUsageEntryCollapsed>>;
+
+/// The reported names of the models that had no price, see `UnknownModelPolicy`.
+pub type Unpriced = BTreeSet<String>;
+
 // 1. [Primitives]: Group by provider. (The Base)
 // 2. [Tokens Group]: Primitives ->   Collapse Tokens -> HashMap
 // 3. [Tokens Sum]:   Tokens Group -> Fold            -> u64
 // 4. [Cost Group]:   Primitives ->   Collapse Cost   -> HashMap
 // 5. [Cost Sum]:     Cost Group   -> Fold            -> u64
-fn _example(buckets: Vec<UnifiedBucketByTime>) -> AppResult<()> {
-    let primitive = make_primitives(buckets)?;
+fn _example(buckets: Vec<UnifiedBucketByTime>, pricing: &[PricingTable]) -> AppResult<()> {
+    let (primitive, _unpriced) = make_primitives(
+        buckets,
+        pricing,
+        &[Grouping::Model],
+        &UnknownModelPolicy::Error,
+        &TimeZone::UTC,
+    )?;
 
     let tokens_group = collapse_tokens(primitive.clone());
     let tokens_sum = fold(collapse_tokens(primitive.clone()));
@@ -78,61 +97,287 @@ fn _example(buckets: Vec<UnifiedBucketByTime>) -> AppResult<()> {
 }
 
 /// Looks up pricing by matching model names from the report api.
-/// It reads the full model name that gets reported (e.g., "claude-sonnet-4-5-datexyz"),
+/// It reads the full model name that gets reported (e.g., "claude-sonnet-4-5-20250929"),
 /// and matches it to our simpler one ("claude-sonnet-4-5").
-/// Panics on missing entries to force me to add them to the table.
-fn find_price(result_entry: &UnifiedUsageEntry) -> AppResult<&PricingTable> {
-    let context_window = &result_entry.context_window;
-
-    // Find the pricing data from the lookup table.
-    let pricing_data = PRICING.iter().find(|table_entry| {
+///
+/// Only a date may follow the base name, see `is_same_model`. The most specific name wins,
+/// so a dated row beats the undated one no matter how the table is ordered. Within that name, the row has to
+/// match the reported context window, unless the row says it applies to "all" of them,
+/// and be in force on `day`, the UTC day the bucket starts.
+/// If several are, the last one wins, which is the pricing file's.
+///
+/// Errors on missing entries to force me (or the user's pricing file) to add them to the table.
+fn find_price<'a>(
+    result_entry: &UnifiedUsageEntry,
+    day: Date,
+    pricing: &'a [PricingTable],
+) -> AppResult<&'a PricingTable> {
+    let reported_model_name = result_entry.model.as_deref().unwrap_or("Unknown");
+    let reported_context_window = result_entry.context_window.as_deref().unwrap_or("Unknown");
+
+    // Every row that could be this model, e.g. both "gpt-4o" and "gpt-4o-2024-05-13".
+    let candidates = pricing.iter().filter(|table_entry| {
         result_entry.model.as_ref().is_some_and(|full_model_name| {
-            // This will match "claude-sonnet-4-5" from the full name "claude-sonnet-4-5-datexyz"
-            full_model_name.starts_with(table_entry.base_model_name)
+            is_same_model(full_model_name, &table_entry.base_model_name)
         })
     });
 
-    let pricing_entry = pricing_data.ok_or_else(|| {
-        let reported_model_name = result_entry.model.as_deref().unwrap_or("Unknown");
-        let reported_context_window = context_window.as_deref().unwrap_or("Unknown");
+    // Then keep only the longest name, the dated one in the example above.
+    let most_specific_rows: Vec<&PricingTable> = candidates
+        .max_set_by_key(|table_entry| table_entry.base_model_name.len())
+        .into_iter()
+        .collect();
 
-        Error::PricingNotFound {
+    if most_specific_rows.is_empty() {
+        bail!(Error::PricingNotFound {
             model: reported_model_name.to_owned(),
             context_window: reported_context_window.to_owned(),
-        }
+        });
+    }
+
+    let same_window_rows = most_specific_rows
+        .iter()
+        .filter(|table_entry| {
+            table_entry.context_window == ANY_CONTEXT_WINDOW
+                || result_entry.context_window.as_deref()
+                    == Some(table_entry.context_window.as_ref())
+        })
+        .collect_vec();
+
+    // The model is known, but not in this context window.
+    if same_window_rows.is_empty() {
+        bail!(Error::PricingTierNotFound {
+            model: reported_model_name.to_owned(),
+            context_window: reported_context_window.to_owned(),
+            known_context_windows: most_specific_rows
+                .iter()
+                .map(|table_entry| &table_entry.context_window)
+                .join(", "),
+        });
+    }
+
+    let pricing_entry = same_window_rows
+        .iter()
+        .rfind(|table_entry| table_entry.is_in_force(day));
+
+    // The model is known in this window, but its prices don't go back (or forward) that far.
+    let pricing_entry = pricing_entry.ok_or_else(|| Error::PricingPeriodNotFound {
+        model: reported_model_name.to_owned(),
+        context_window: reported_context_window.to_owned(),
+        day: day.to_string(),
+        known_periods: same_window_rows
+            .iter()
+            .map(|table_entry| table_entry.effective_period())
+            .join(", "),
     })?;
 
     Ok(pricing_entry)
 }
 
-/// It's a model name + usage entry.
-/// Example: ('the-model-name-4-5', content)
-type BaseModelUsageEntryPair = (String, UnifiedUsageEntry);
+/// Whether a reported name is this base model, as it is or with a date.
+///
+/// "claude-sonnet-4-5-20250929" and "gpt-4o-2024-08-06" are, but "gpt-5-pro" isn't "gpt-5",
+/// it's a different model at ten times the price.
+fn is_same_model(full_model_name: &str, base_model_name: &str) -> bool {
+    let Some(suffix) = full_model_name.strip_prefix(base_model_name) else {
+        return false;
+    };
+
+    let is_date = |date: &str, dashes: &[usize]| {
+        date.char_indices().all(|(index, character)| {
+            if dashes.contains(&index) {
+                character == '-'
+            } else {
+                character.is_ascii_digit()
+            }
+        })
+    };
+
+    match suffix.strip_prefix('-') {
+        None => suffix.is_empty(),
+        // -YYYYMMDD
+        Some(date) if date.len() == 8 => is_date(date, &[]),
+        // -YYYY-MM-DD
+        Some(date) if date.len() == 10 => is_date(date, &[4, 7]),
+        Some(_) => false,
+    }
+}
+
+/// Looks up the row an unpriced model is priced like, with `fallback=<model>`.
+///
+/// The name has to be exact, it's typed by the user. The row for the entry's context window
+/// wins, but a model of another provider may not know that window at all, so any rate of it
+/// beats none. Only the rows in force on `day` count, unless none is.
+fn find_fallback_price<'a>(
+    result_entry: &UnifiedUsageEntry,
+    day: Date,
+    fallback_model: &str,
+    pricing: &'a [PricingTable],
+) -> AppResult<&'a PricingTable> {
+    let rows = pricing
+        .iter()
+        .filter(|table_entry| table_entry.base_model_name == fallback_model)
+        .collect_vec();
+    let rows_in_force = rows
+        .iter()
+        .copied()
+        .filter(|table_entry| table_entry.is_in_force(day))
+        .collect_vec();
+    let rows = if rows_in_force.is_empty() {
+        rows
+    } else {
+        rows_in_force
+    };
+
+    let same_window = rows.iter().rfind(|table_entry| {
+        table_entry.context_window == ANY_CONTEXT_WINDOW
+            || result_entry.context_window.as_deref() == Some(table_entry.context_window.as_ref())
+    });
+
+    let pricing_entry = same_window
+        .or(rows.last())
+        .ok_or_else(|| Error::FallbackModelNotFound(fallback_model.to_owned()))?;
+
+    Ok(pricing_entry)
+}
 
-/// Extracts base model names as keys for each usage result.
+/// A usage entry, already converted into money using its own pricing row.
+struct PricedUsageEntry {
+    entry: UnifiedUsageEntry,
+    cost: f64,
+}
+
+/// It's a bucket start, in unix seconds + usage entry.
+type TimedUsageEntry = (i64, UnifiedUsageEntry);
+
+/// It's a group key + priced usage entry.
+/// Example: (['the-model-name-4-5'], content), when grouping by model.
+type GroupKeyUsageEntryPair = (GroupKey, PricedUsageEntry);
+
+/// Extracts group keys for each usage result.
 ///
 /// Transforms results into (key, value) tuples compatible with itertools `into_grouping_map()`,
-/// where keys are base model names from the pricing table.
+/// where keys are the values of the requested grouping dimensions, e.g. base model names from
+/// the pricing table, or workspace ids. No dimensions means a single empty key for everything.
 ///
-/// This also helps validate that each reporting model name exists in the pricing table.
-/// Returns Err immediately if any model is not found.
-fn try_into_base_model_pairs(
-    results: Vec<UnifiedUsageEntry>,
-) -> AppResult<Vec<BaseModelUsageEntryPair>> {
-    results
-        .into_iter()
-        .map(|entry| {
-            let pricing = find_price(&entry)?;
-            let key = pricing.base_model_name.to_owned();
-            Ok((key, entry))
-        })
-        .collect()
+/// This also validates that each reporting model name exists in the pricing table.
+/// What happens to the ones that don't is up to `on_unknown_model`, their names go into
+/// `unpriced` unless they fall back to another model's price.
+///
+/// Each entry is priced here, before anything gets collapsed, because entries of the same
+/// model can still belong to different context windows, which have different rates,
+/// and to buckets from before and after a price change.
+fn try_into_group_key_pairs(
+    provider: &Provider,
+    results: Vec<TimedUsageEntry>,
+    pricing: &[PricingTable],
+    groupings: &[Grouping],
+    on_unknown_model: &UnknownModelPolicy,
+    time_zone: &TimeZone,
+    unpriced: &mut Unpriced,
+) -> AppResult<Vec<GroupKeyUsageEntryPair>> {
+    let mut pairs = vec![];
+
+    for (bucket_start, entry) in results {
+        let reported_model_name = entry.model.as_deref().unwrap_or("Unknown").to_owned();
+        let bucket_start = Timestamp::from_second(bucket_start).into_diagnostic()?;
+
+        // Prices change on UTC days, whatever the time zone of the report.
+        let day = bucket_start.to_zoned(TimeZone::UTC).date();
+
+        // Priced models go by their name in the table, the others by the reported one.
+        let (model_name, pricing_entry) = match find_price(&entry, day, pricing) {
+            Ok(pricing_entry) => (
+                pricing_entry.base_model_name.to_string(),
+                Some(pricing_entry),
+            ),
+            Err(e) => match on_unknown_model {
+                UnknownModelPolicy::Error => return Err(e),
+                UnknownModelPolicy::Skip => {
+                    unpriced.insert(reported_model_name);
+
+                    continue;
+                }
+                UnknownModelPolicy::Zero => {
+                    unpriced.insert(reported_model_name.clone());
+
+                    (reported_model_name, None)
+                }
+                UnknownModelPolicy::Fallback(fallback_model) => {
+                    let pricing_entry = find_fallback_price(&entry, day, fallback_model, pricing)?;
+
+                    (reported_model_name, Some(pricing_entry))
+                }
+            },
+        };
+
+        let bucket_start = bucket_start.to_zoned(time_zone.clone());
+        let key = groupings
+            .iter()
+            .map(|grouping| group_key(grouping, provider, &model_name, &entry, &bucket_start))
+            .collect::<AppResult<GroupKey>>()?;
+        let cost = pricing_entry.map_or(0.0, |pricing_entry| {
+            calculate_entry_cost(&entry, pricing_entry)
+        });
+
+        pairs.push((key, PricedUsageEntry { entry, cost }));
+    }
+
+    Ok(pairs)
+}
+
+/// Reads the value of a grouping dimension off a usage entry.
+///
+/// Missing values are labelled rather than dropped, so the totals still add up.
+/// Anthropic reports the default workspace as a null workspace id.
+///
+/// Time periods are labelled by their first moment, in a format that sorts as text,
+/// e.g. "2025-01-31" for a day, or "2025-01-27" for the week starting that Monday.
+fn group_key(
+    grouping: &Grouping,
+    provider: &Provider,
+    model_name: &str,
+    entry: &UnifiedUsageEntry,
+    bucket_start: &Zoned,
+) -> AppResult<String> {
+    let unknown = || "Unknown".to_owned();
+
+    let key = match grouping {
+        Grouping::Model => model_name.to_owned(),
+        Grouping::Provider => provider.to_string(),
+        Grouping::Workspace => entry
+            .workspace_id
+            .clone()
+            .unwrap_or_else(|| "default".to_owned()),
+        Grouping::ApiKey => entry.api_key_id.clone().unwrap_or_else(unknown),
+        Grouping::ServiceTier => entry.service_tier.clone().unwrap_or_else(unknown),
+        Grouping::ContextWindow => entry.context_window.clone().unwrap_or_else(unknown),
+        // The minutes too, the hours start at half past in India, for example.
+        Grouping::Hour => bucket_start.strftime("%Y-%m-%d %H:%M").to_string(),
+        Grouping::Day => bucket_start.strftime("%Y-%m-%d").to_string(),
+        Grouping::Week => {
+            let date = bucket_start.date();
+            let days_since_monday = date.weekday().to_monday_zero_offset();
+            let monday = date
+                .checked_sub(Span::new().days(days_since_monday))
+                .into_diagnostic()?;
+
+            monday.strftime("%Y-%m-%d").to_string()
+        }
+        Grouping::Month => bucket_start.strftime("%Y-%m").to_string(),
+    };
+
+    Ok(key)
 }
 
 /// Creates the "Primitive" state.
 ///
-/// Collapses time-bucketed usage data into a nested map indexed by Provider and Model.
-/// This aggregates token counts (uncached, cached, and output) across all time buckets.
+/// Collapses time-bucketed usage data into a nested map indexed by Provider and the grouping
+/// dimensions, in the order they were asked for. Time periods follow the given time zone.
+/// This aggregates token counts (uncached, cached, and output) and costs across all time
+/// buckets.
+///
+/// Models without a price are handled by `on_unknown_model`, and returned alongside.
 ///
 /// Any future calculations should start from this return value.
 /// Terminology:
@@ -140,63 +385,81 @@ fn try_into_base_model_pairs(
 /// - fold -> fold rows, from bottom, vertically.
 pub fn make_primitives(
     buckets: Vec<UnifiedBucketByTime>,
-) -> AppResult<HashMap<Provider, HashMap<String, UnifiedUsageEntryCollapsed>>> {
+    pricing: &[PricingTable],
+    groupings: &[Grouping],
+    on_unknown_model: &UnknownModelPolicy,
+    time_zone: &TimeZone,
+) -> AppResult<(Primitives, Unpriced)> {
+    let mut unpriced = Unpriced::new();
+
     let usage_by_provider = collapse_by_providers(buckets).into_iter().try_fold(
         HashMap::new(),
         |mut providers_map, (provider, entries)| -> AppResult<_> {
-            let usage_entries_by_model = try_into_base_model_pairs(entries)?;
-
-            let collapsed_usage_by_model =
-                usage_entries_by_model.into_iter().into_grouping_map().fold(
+            let usage_entries_by_group = try_into_group_key_pairs(
+                &provider,
+                entries,
+                pricing,
+                groupings,
+                on_unknown_model,
+                time_zone,
+                &mut unpriced,
+            )?;
+
+            let collapsed_usage_by_group =
+                usage_entries_by_group.into_iter().into_grouping_map().fold(
                     UnifiedUsageEntryCollapsed::default(),
-                    |mut collapsed, _model_name, entry| {
+                    |mut collapsed, _group_key, PricedUsageEntry { entry, cost }| {
                         collapsed.uncached_input_tokens += entry.uncached_input_tokens;
                         collapsed.cache_read_input_tokens += entry.cache_read_input_tokens;
+                        collapsed.cache_creation.ephemeral_1h_input_tokens +=
+                            entry.cache_creation.ephemeral_1h_input_tokens;
+                        collapsed.cache_creation.ephemeral_5m_input_tokens +=
+                            entry.cache_creation.ephemeral_5m_input_tokens;
                         collapsed.output_tokens += entry.output_tokens;
+                        collapsed.cost += cost;
                         collapsed.model = entry.model.unwrap_or("Unknown".to_owned());
 
                         collapsed
                     },
                 );
 
-            providers_map.insert(provider, collapsed_usage_by_model);
+            providers_map.insert(provider, collapsed_usage_by_group);
 
             Ok(providers_map)
         },
     )?;
 
-    Ok(usage_by_provider)
+    Ok((usage_by_provider, unpriced))
 }
 
 /// Primitives -> Tokens HashMap
 ///
-/// Transforms the Primitive data into a flat Provider -> Total Tokens map.
-pub fn collapse_tokens(
-    primitive: HashMap<Provider, HashMap<String, UnifiedUsageEntryCollapsed>>,
-) -> HashMap<String, u64> {
-    let tokens_by_provider_then_model = primitive.into_iter().fold(
+/// Transforms the Primitive data into a flat Group -> Total Tokens map.
+pub fn collapse_tokens(primitive: Primitives) -> HashMap<GroupKey, u64> {
+    let tokens_by_provider_then_group = primitive.into_iter().fold(
         HashMap::new(),
-        |mut providers_map, (provider, usage_entry_collapsed_by_model)| {
-            let tokens_count_by_model = usage_entry_collapsed_by_model.into_iter().fold(
+        |mut providers_map, (provider, usage_entry_collapsed_by_group)| {
+            let tokens_count_by_group = usage_entry_collapsed_by_group.into_iter().fold(
                 HashMap::new(),
-                |mut models_map, (base_model_name, entry)| -> HashMap<String, u64> {
+                |mut groups_map, (group_key, entry)| -> HashMap<GroupKey, u64> {
                     let tokens_counts = entry.uncached_input_tokens
                         + entry.cache_read_input_tokens
+                        + entry.cache_creation.total_input_tokens()
                         + entry.output_tokens;
 
-                    models_map.insert(base_model_name, tokens_counts);
+                    groups_map.insert(group_key, tokens_counts);
 
-                    models_map
+                    groups_map
                 },
             );
 
-            providers_map.insert(provider, tokens_count_by_model);
+            providers_map.insert(provider, tokens_count_by_group);
 
             providers_map
         },
     );
 
-    tokens_by_provider_then_model
+    tokens_by_provider_then_group
         .into_values()
         .flatten()
         .into_grouping_map()
@@ -206,7 +469,7 @@ pub fn collapse_tokens(
 /// Reduction: HashMap -> Number
 ///
 /// Sums the pre-calculated products of each row into a single value.
-pub fn fold<T>(some_map: HashMap<String, T>) -> T
+pub fn fold<K, T>(some_map: HashMap<K, T>) -> T
 where
     T: std::iter::Sum,
 {
@@ -215,55 +478,49 @@ where
 
 /// Primitives -> Cost HashMap
 ///
-/// Transforms the Primitive data into a Model -> Cost map.
+/// Transforms the Primitive data into a Group -> Cost map (Model -> Cost by default).
 ///
-/// Costs are computed using the global `PRICING` table.
-/// This is where the conversion from Integer Tokens to Float Money happens
-pub fn collapse_cost(
-    primitive: HashMap<Provider, HashMap<String, UnifiedUsageEntryCollapsed>>,
-) -> HashMap<String, f64> {
-    let costs_iter = primitive
-        .into_iter()
-        .flat_map(|(_provider, usage_by_model)| {
-            usage_by_model.into_iter().map(|(base_model_name, entry)| {
-                // Safety: Since `keyed_results` has validated the price existence in the table,
-                // we can safely unwrap the pricing entry.
-                let pricing = PRICING
-                    .iter()
-                    .find(|table_entry| table_entry.base_model_name == base_model_name)
-                    .unwrap();
-
-                let total_input_tokens =
-                    entry.uncached_input_tokens + entry.cache_read_input_tokens;
-                let total_output_tokens = entry.output_tokens;
-
-                let input_cost = calculate_cost(total_input_tokens, pricing.input_multiplier);
-                let output_cost = calculate_cost(total_output_tokens, pricing.output_multiplier);
-
-                (base_model_name, input_cost + output_cost)
-            })
-        });
+/// The money was already counted per entry while making the primitives,
+/// see `try_into_group_key_pairs`, so this only has to gather it.
+pub fn collapse_cost(primitive: Primitives) -> HashMap<GroupKey, f64> {
+    let costs_iter = primitive.into_values().flat_map(|usage_by_group| {
+        usage_by_group
+            .into_iter()
+            .map(|(group_key, entry)| (group_key, entry.cost))
+    });
 
     // This ensures field uniqueness, if there are duplicates, sum their values.
     // In case the same model name comes from multiple providers.
-    // Example: { model-a: 2, model-a: 3 } -> { model-a: 5 }
+    // Example: { [model-a]: 2, [model-a]: 3 } -> { [model-a]: 5 }
     costs_iter.into_grouping_map().sum()
 }
 
 /// Vector of usage entry, groupped by provider.
+///
+/// Each entry keeps the start of the bucket it came from, so it can still be grouped by time.
 fn collapse_by_providers(
     buckets: Vec<UnifiedBucketByTime>,
-) -> HashMap<Provider, Vec<UnifiedUsageEntry>> {
+) -> HashMap<Provider, Vec<TimedUsageEntry>> {
     buckets
         .into_iter()
         .map(
             |UnifiedBucketByTime {
-                 provider, results, ..
-             }| { (provider, results) },
+                 provider,
+                 results,
+                 start,
+                 ..
+             }| {
+                let timed_results = results
+                    .into_iter()
+                    .map(|entry| (start, entry))
+                    .collect::<Vec<TimedUsageEntry>>();
+
+                (provider, timed_results)
+            },
         )
         .into_group_map()
         .into_iter()
-        .map(|(key, value)| -> (Provider, Vec<UnifiedUsageEntry>) {
+        .map(|(key, value)| -> (Provider, Vec<TimedUsageEntry>) {
             let joined_vectors = value.into_iter().flatten().collect();
 
             (key, joined_vectors)
@@ -271,6 +528,34 @@ fn collapse_by_providers(
         .collect()
 }
 
+/// Converts every kind of token in the entry into money, using the given pricing row.
+///
+/// This is where the conversion from Integer Tokens to Float Money happens.
+fn calculate_entry_cost(entry: &UnifiedUsageEntry, pricing: &PricingTable) -> f64 {
+    // Every kind of input token has its own rate, cache reads are the cheap ones,
+    // and cache writes cost more than a plain input, depending on how long they live.
+    let uncached_input_cost = calculate_cost(entry.uncached_input_tokens, pricing.input_multiplier);
+    let cache_read_cost =
+        calculate_cost(entry.cache_read_input_tokens, pricing.cache_read_multiplier);
+    let cache_write_5m_cost = calculate_cost(
+        entry.cache_creation.ephemeral_5m_input_tokens,
+        pricing.cache_write_5m_multiplier,
+    );
+    let cache_write_1h_cost = calculate_cost(
+        entry.cache_creation.ephemeral_1h_input_tokens,
+        pricing.cache_write_1h_multiplier,
+    );
+    let output_cost = calculate_cost(entry.output_tokens, pricing.output_multiplier);
+
+    let input_cost =
+        uncached_input_cost + cache_read_cost + cache_write_5m_cost + cache_write_1h_cost;
+
+    // Batches and the like discount the whole entry, not a kind of token.
+    let service_tier_multiplier = pricing.service_tier_multiplier(entry.service_tier.as_deref());
+
+    (input_cost + output_cost) * service_tier_multiplier
+}
+
 fn calculate_cost(tokens: u64, price_per_million: f64) -> f64 {
     let tokens_in_millions = tokens as f64 / 1_000_000.0;
 
@@ -296,3 +581,161 @@ fn calculate_cost(tokens: u64, price_per_million: f64) -> f64 {
 //         })
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::pricing_table::PRICING;
+    use jiff::civil::date;
+
+    /// A day every built-in price of these tests applies on.
+    const TODAY: Date = date(2025, 12, 1);
+
+    fn entry(model: &str, context_window: Option<&str>) -> UnifiedUsageEntry {
+        UnifiedUsageEntry {
+            model: Some(model.to_owned()),
+            context_window: context_window.map(str::to_owned),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_price_resolves_every_row_in_the_table() {
+        for row in PRICING {
+            // The dated suffix the api usually reports, e.g. "claude-sonnet-4-5-20250929".
+            let reported_model = format!("{}-20250929", row.base_model_name);
+            let reported_window =
+                (row.context_window != ANY_CONTEXT_WINDOW).then_some(row.context_window.as_ref());
+
+            // A day the row is in force.
+            let day = match (row.effective_from, row.effective_until) {
+                (Some(from), _) => from,
+                (None, Some(until)) => until.yesterday().unwrap(),
+                (None, None) => TODAY,
+            };
+
+            let found = find_price(&entry(&reported_model, reported_window), day, PRICING).unwrap();
+
+            assert!(
+                std::ptr::eq(found, row),
+                "{reported_model} ({reported_window:?}) resolved to {found:?}, expected {row:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn find_price_prefers_the_longest_model_name() {
+        let sonnet_4_5 = entry("claude-sonnet-4-5-20250929", Some("0-200k"));
+        let found = find_price(&sonnet_4_5, TODAY, PRICING).unwrap();
+        assert_eq!(found.base_model_name, "claude-sonnet-4-5");
+
+        let sonnet_4 = entry("claude-sonnet-4-20250514", Some("0-200k"));
+        let found = find_price(&sonnet_4, TODAY, PRICING).unwrap();
+        assert_eq!(found.base_model_name, "claude-sonnet-4");
+    }
+
+    #[test]
+    fn find_price_ignores_other_models_that_share_a_prefix() {
+        for model in [
+            "gpt-5-pro-2025-10-06",
+            "o3-pro-2025-06-10",
+            "gpt-4o-audio-preview",
+        ] {
+            let report = find_price(&entry(model, None), TODAY, PRICING).unwrap_err();
+
+            assert!(
+                matches!(
+                    report.downcast_ref::<Error>(),
+                    Some(Error::PricingNotFound { .. })
+                ),
+                "{model} shouldn't have a price"
+            );
+        }
+    }
+
+    #[test]
+    fn find_price_prefers_a_dated_row() {
+        let gpt_4o = entry("gpt-4o-2024-05-13", None);
+        let found = find_price(&gpt_4o, TODAY, PRICING).unwrap();
+        assert_eq!(found.base_model_name, "gpt-4o-2024-05-13");
+
+        let gpt_4o = entry("gpt-4o-2024-08-06", None);
+        let found = find_price(&gpt_4o, TODAY, PRICING).unwrap();
+        assert_eq!(found.base_model_name, "gpt-4o");
+    }
+
+    #[test]
+    fn find_price_uses_the_long_context_rate() {
+        let long_context = entry("claude-sonnet-4-5-20250929", Some("200k-1M"));
+        let found = find_price(&long_context, TODAY, PRICING).unwrap();
+
+        assert_eq!(found.context_window, "200k-1M");
+    }
+
+    #[test]
+    fn find_price_reports_a_missing_context_window() {
+        let long_context = entry("claude-opus-4-5-20251101", Some("200k-1M"));
+        let report = find_price(&long_context, TODAY, PRICING).unwrap_err();
+
+        assert!(matches!(
+            report.downcast_ref::<Error>(),
+            Some(Error::PricingTierNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn find_price_reports_an_unknown_model() {
+        let unknown = entry("claude-instant-1", Some("0-200k"));
+        let report = find_price(&unknown, TODAY, PRICING).unwrap_err();
+
+        assert!(matches!(
+            report.downcast_ref::<Error>(),
+            Some(Error::PricingNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn find_price_uses_the_price_in_force_on_the_day() {
+        let o3 = entry("o3-2025-04-16", None);
+
+        let found = find_price(&o3, date(2025, 6, 9), PRICING).unwrap();
+        assert_eq!(found.input_multiplier, 10.0);
+
+        let found = find_price(&o3, date(2025, 6, 10), PRICING).unwrap();
+        assert_eq!(found.input_multiplier, 2.0);
+    }
+
+    #[test]
+    fn find_price_reports_a_day_without_a_price() {
+        let mut pricing = PRICING.to_vec();
+        pricing.retain(|row| row.base_model_name != "o3" || row.effective_from.is_some());
+
+        let o3 = entry("o3-2025-04-16", None);
+        let report = find_price(&o3, date(2025, 5, 1), &pricing).unwrap_err();
+
+        assert!(matches!(
+            report.downcast_ref::<Error>(),
+            Some(Error::PricingPeriodNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn group_key_labels_hours_by_their_first_minute() {
+        let bucket_start = Timestamp::from_second(1_792_173_600) // 2026-10-16 18:00 UTC
+            .unwrap()
+            .to_zoned(TimeZone::get("Asia/Kolkata").unwrap());
+        let key = |grouping| {
+            group_key(
+                &grouping,
+                &Provider::Anthropic,
+                "claude-sonnet-4-5",
+                &entry("claude-sonnet-4-5", None),
+                &bucket_start,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(key(Grouping::Hour), "2026-10-16 23:30");
+        assert_eq!(key(Grouping::Day), "2026-10-16");
+    }
+}
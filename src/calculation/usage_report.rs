@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use itertools::Itertools;
 use std::collections::HashMap;
 
 /// Represents usage data in different formats for reporting.
@@ -11,12 +12,30 @@ pub enum UsageReport {
     Token(u64),
     /// Total cost in dollars.
     Money(f64),
-    /// Nested usage data, typically grouped by model name.
+    /// Nested usage data, one level per grouping dimension, typically by model name.
     Map(HashMap<String, UsageReport>),
     /// Raw JSON dump for the raw command.
     Raw(String),
+    /// What the providers that made it add up to, with `--partial`.
+    /// The ones that didn't are listed, so the number isn't mistaken for the whole bill.
+    Partial {
+        report: Box<UsageReport>,
+        missing: Vec<String>,
+        /// Why they are missing, for stderr.
+        #[serde(skip)]
+        errors: Vec<miette::Report>,
+    },
+    /// A report some models couldn't be priced for, with `--on-unknown-model zero` or `skip`.
+    /// They are listed, so a short number is never taken for the whole thing.
+    Unpriced {
+        report: Box<UsageReport>,
+        models: Vec<String>,
+    },
 }
 
+/// Follows the name of a provider missing from a partial report, or of an unpriced model.
+const MISSING_MARKER: &str = "⚠";
+
 impl UsageReport {
     /// Renders the report into a string based on its variant.
     /// - Maps become CSV data.
@@ -30,31 +49,119 @@ impl UsageReport {
             // Map reports: Serialize to CSV.
             UsageReport::Map(_hp) => self.format_csv(no_format),
 
-            _ => unreachable!(
-                "UsageReport::render: Unhandled variant. Did you add a new report type?"
-            ),
+            // Already JSON, formatted or not.
+            UsageReport::Raw(json) => Ok(json.clone()),
+
+            // Partial reports: the report, then the missing providers.
+            // "$12.40 · openai ⚠" on one line, a row of their own after CSV rows.
+            // Raw stays valid JSON, the exit code tells it's partial.
+            UsageReport::Partial {
+                report, missing, ..
+            } => {
+                let rendered = report.render(no_format, with_symbol)?;
+                let marked = missing
+                    .iter()
+                    .map(|provider| format!("{provider} {MISSING_MARKER}"))
+                    .collect_vec();
+
+                match (&**report, report.table_depth()) {
+                    (UsageReport::Raw(_), _) => Ok(rendered),
+                    (_, Some(depth)) => {
+                        Ok(format!("{rendered}{}", Self::footer_csv(depth, &marked)?))
+                    }
+                    (_, None) => Ok(format!("{rendered} · {}", marked.join(" · "))),
+                }
+            }
+
+            // Unpriced reports: same idea, the models that aren't in the number.
+            // "$12.40 · ⚠ unpriced: gpt-9, o7" on one line, a row each after CSV rows.
+            UsageReport::Unpriced { report, models } => {
+                let rendered = report.render(no_format, with_symbol)?;
+
+                if let Some(depth) = report.table_depth() {
+                    let marked = models
+                        .iter()
+                        .map(|model| format!("{model} {MISSING_MARKER} unpriced"))
+                        .collect_vec();
+
+                    return Ok(format!("{rendered}{}", Self::footer_csv(depth, &marked)?));
+                }
+
+                Ok(format!(
+                    "{rendered} · {MISSING_MARKER} unpriced: {}",
+                    models.join(", ")
+                ))
+            }
         }
     }
 
-    /// Internal helper: Serializes map data into a valid CSV string.
-    fn format_csv(&self, no_format: bool) -> AppResult<String> {
+    /// How many key columns its CSV rows have, `None` if it renders as a single value.
+    fn table_depth(&self) -> Option<usize> {
         match self {
             UsageReport::Map(hp) => {
-                /// Temporary struct to define the CSV column layout for render groupping.
-                /// There are 2 columns: entity name and formatted value.
-                #[derive(Serialize)]
-                struct CsvRow {
-                    /// Left column, for an entity name.
-                    display_name: String,
-                    /// Right column, for formatted value.
-                    content: String,
-                }
+                let child_depth = hp.values().next().and_then(Self::table_depth);
 
+                Some(1 + child_depth.unwrap_or(0))
+            }
+            UsageReport::Partial { report, .. } | UsageReport::Unpriced { report, .. } => {
+                report.table_depth()
+            }
+            _ => None,
+        }
+    }
+
+    /// Rows that follow a table, one per label, shaped like its rows.
+    ///
+    /// The label goes where the entity name goes, the parent keys and the value stay empty,
+    /// so `workspace,model,cost` is still three columns all the way down.
+    /// `depth` is how many key columns the table has, one per grouping.
+    pub fn footer_csv(depth: usize, labels: &[String]) -> AppResult<String> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+
+        for label in labels {
+            let record = std::iter::repeat_n("", depth - 1).chain([label.as_str(), ""]);
+
+            writer
+                .write_record(record)
+                .into_diagnostic()
+                .wrap_err("Failed to serialize a footer row to CSV format")?;
+        }
+
+        let data = writer
+            .into_inner()
+            .into_diagnostic()
+            .wrap_err("Failed to get writer data.")?;
+
+        String::from_utf8(data)
+            .into_diagnostic()
+            .wrap_err("Invalid utf-8")
+    }
+
+    /// Internal helper: Serializes map data into a valid CSV string.
+    ///
+    /// Nested maps become extra columns, one per grouping level, followed by the value.
+    /// Example: `workspace,model,cost`.
+    fn format_csv(&self, no_format: bool) -> AppResult<String> {
+        match self {
+            UsageReport::Map(_) => {
                 let mut writer = csv::WriterBuilder::new()
                     .has_headers(false) // I don't want a header.
                     .from_writer(vec![]);
 
-                for (key, value) in hp {
+                let mut rows = vec![];
+                self.collect_rows(vec![], &mut rows);
+
+                // HashMaps have no order, sort them so the output is stable between runs.
+                rows.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+                for (keys, value) in rows {
+                    // Every column but the last one is an entity name.
+                    let (last_key, parent_keys) = keys
+                        .split_last()
+                        .expect("Logic error: a map row always has a key.");
+
                     let (display_name, content) = match value {
                         // This is a special case when rendering money inside csv.
                         //
@@ -73,22 +180,22 @@ impl UsageReport {
                             let cost_without_symbol = value.render(no_format, Some(false))?;
 
                             // We need this -> model-name-123 ($1.23).
-                            let display_column = format!("{} ({})", key, cost_with_symbol);
+                            let display_column = format!("{} ({})", last_key, cost_with_symbol);
 
                             // We don't need the right column to have a dollar sign as it breaks the
                             // program like uplot.
                             (display_column, cost_without_symbol)
                         }
-                        _ => (key.clone(), value.render(no_format, None)?), // Just passing them along.
+                        _ => (last_key.to_string(), value.render(no_format, None)?), // Just passing them along.
                     };
 
-                    let row = CsvRow {
-                        display_name,
-                        content,
-                    };
+                    let record = parent_keys
+                        .iter()
+                        .map(|key| key.to_string())
+                        .chain([display_name, content]);
 
                     writer
-                        .serialize(row)
+                        .write_record(record)
                         .into_diagnostic()
                         .wrap_err("Failed to serialize grouped data row to CSV format")?;
                 }
@@ -109,6 +216,53 @@ impl UsageReport {
         }
     }
 
+    /// Internal helper: Walks nested maps down to their values, remembering the keys on the way.
+    fn collect_rows<'a>(&'a self, keys: Vec<&'a str>, rows: &mut Vec<(Vec<&'a str>, &'a Self)>) {
+        match self {
+            UsageReport::Map(hp) => {
+                for (key, value) in hp {
+                    let mut child_keys = keys.clone();
+                    child_keys.push(key);
+
+                    value.collect_rows(child_keys, rows);
+                }
+            }
+            _ => rows.push((keys, self)),
+        }
+    }
+
+    /// Internal helper: Builds nested maps out of flat group keys.
+    ///
+    /// Example: `{ ["ws-1", "model-a"]: 1.23 }` -> `{ "ws-1": { "model-a": 1.23 } }`
+    ///
+    /// An empty key means nothing was grouped, so its value is the whole report.
+    fn nest(rows: impl IntoIterator<Item = (Vec<String>, UsageReport)>) -> Self {
+        let mut root = HashMap::new();
+
+        for (keys, value) in rows {
+            let Some((last_key, parent_keys)) = keys.split_last() else {
+                return value;
+            };
+
+            let mut level = &mut root;
+
+            for parent_key in parent_keys {
+                let child = level
+                    .entry(parent_key.clone())
+                    .or_insert_with(|| UsageReport::Map(HashMap::new()));
+
+                level = match child {
+                    UsageReport::Map(map) => map,
+                    _ => unreachable!("Logic error: group keys must all have the same length."),
+                };
+            }
+
+            level.insert(last_key.clone(), value);
+        }
+
+        UsageReport::Map(root)
+    }
+
     // Internal helper: Formats numeric variants, optionally removing the unit.
 
     fn render_token(value: &u64) -> String {
@@ -149,26 +303,18 @@ impl From<u64> for UsageReport {
 
 /// Converts a map of costs (e.g., per-model) into a nested report.
 ///
-/// Example: `{ "model-a": 1.23, "model-b": 4.56 }`
-impl From<HashMap<String, f64>> for UsageReport {
-    fn from(map: HashMap<String, f64>) -> Self {
-        let converted = map
-            .into_iter()
-            .map(|(k, v)| (k, UsageReport::Money(v)))
-            .collect();
-        UsageReport::Map(converted)
+/// Example: `{ ["model-a"]: 1.23, ["model-b"]: 4.56 }`
+impl From<HashMap<Vec<String>, f64>> for UsageReport {
+    fn from(map: HashMap<Vec<String>, f64>) -> Self {
+        UsageReport::nest(map.into_iter().map(|(k, v)| (k, UsageReport::Money(v))))
     }
 }
 
 /// Converts a map of token counts (e.g., per-model) into a nested report.
 ///
-/// Example: `{ "model-a": 1000, "model-b": 2000 }`
-impl From<HashMap<String, u64>> for UsageReport {
-    fn from(map: HashMap<String, u64>) -> Self {
-        let converted = map
-            .into_iter()
-            .map(|(k, v)| (k, UsageReport::Token(v)))
-            .collect();
-        UsageReport::Map(converted)
+/// Example: `{ ["ws-1", "model-a"]: 1000, ["ws-1", "model-b"]: 2000 }`
+impl From<HashMap<Vec<String>, u64>> for UsageReport {
+    fn from(map: HashMap<Vec<String>, u64>) -> Self {
+        UsageReport::nest(map.into_iter().map(|(k, v)| (k, UsageReport::Token(v))))
     }
 }
@@ -1,7 +1,8 @@
 use clap::{Parser, Subcommand, ValueEnum};
-use itertools::Itertools;
+use jiff::tz::TimeZone;
 
 use crate::error::Error;
+use crate::io::source::{AnthropicArgs, OpenaiArgs};
 use crate::prelude::*;
 
 impl Cli {
@@ -10,104 +11,27 @@ impl Cli {
         Cli::parse()
     }
 
-    // A poor man's solution for a credentials store.
-    // I will later come back to it to improve if I add more providers.
-    // Just platforming it now so I can understand the big picture easily in the future.
-    pub fn try_get_anthropic_key(&self) -> AppResult<&String> {
-        let key = self
-            .anthropic_admin_api_key
-            .as_ref()
-            .ok_or(Error::AnthropicKeyNotFound)?;
-
-        Ok(key)
-    }
-
     /// An another poor man's solution to the compact date range string parser.
-    /// Return a number of day user put in.
-    /// Only support day unit for now.
-    pub fn try_parse_since(&self) -> AppResult<u64> {
-        let since = &self.since;
-
-        let Some(digits) = since.strip_suffix('d') else {
-            let error = Error::UnsupportedTimeUnit(since.to_owned());
-
-            return Err(error.into());
-        };
-
-        let numbers = digits
-            .parse::<u64>()
-            .map_err(|_| Error::InvalidDuration(digits.to_owned()))?;
-
-        Ok(numbers)
+    /// Returns where the report starts, see `TimeBound` for what it accepts.
+    pub fn try_parse_since(&self) -> AppResult<TimeBound> {
+        parse_time_bound(&self.since)
     }
 
-    /// Loads API keys for service providers based on user selection.
-    ///
-    /// If the user explicitly chose providers, this returns an error if any are missing keys.
-    /// If no providers were specified, it returns all providers that have keys available
-    /// and ignores those that don't.
-    pub fn load_providers(&self) -> AppResult<Vec<ProviderKeyPair>> {
-        match self.user_selected_providers() {
-            // Strict mode: user specified providers, error if keys missing.
-            Some(validated_user_inputs) => self
-                .provider_blueprints()
-                .into_iter()
-                .filter(|ProviderSpec { provider, .. }| validated_user_inputs.contains(provider))
-                .map(
-                    |ProviderSpec {
-                         provider,
-                         key,
-                         missing_key_error,
-                     }| {
-                        match key {
-                            Some(key_found) => Ok((provider, key_found)),
-                            None => Err(missing_key_error.into()),
-                        }
-                    },
-                )
-                .collect::<AppResult<Vec<ProviderKeyPair>>>(),
-
-            // Auto mode: No providers specified by user.
-            // Return all providers that have API keys available.
-            // Silently skip providers without keys.
-            None => {
-                let available_provider = self
-                    .provider_blueprints()
-                    .into_iter()
-                    .filter_map(|ProviderSpec { provider, key, .. }| {
-                        key.map(|key_found| (provider, key_found))
-                    })
-                    .collect();
-
-                Ok(available_provider)
-            }
-        }
+    /// Same as `try_parse_since`, but for the optional end of the report.
+    pub fn try_parse_until(&self) -> AppResult<Option<TimeBound>> {
+        self.until.as_deref().map(parse_time_bound).transpose()
     }
 
-    /// Cleaned-up version of user inputs due to potential duplicates.
-    fn user_selected_providers(&self) -> Option<Vec<Provider>> {
-        let providers = self.provider.as_ref()?;
-        let deduplicated = providers.iter().unique().cloned().collect();
+    /// The timezone the report boundaries and time groups follow.
+    /// Falls back to the system timezone.
+    pub fn try_get_time_zone(&self) -> AppResult<TimeZone> {
+        let Some(name) = self.tz.as_deref() else {
+            return Ok(TimeZone::system());
+        };
 
-        Some(deduplicated)
-    }
+        let time_zone = TimeZone::get(name).map_err(|_| Error::UnknownTimeZone(name.to_owned()))?;
 
-    /// Returns a list of supported providers and their associated API keys and errors.
-    /// Configure them right here.
-    /// I should move this into src/config/ in the future, maybe.
-    pub fn provider_blueprints(&self) -> [ProviderSpec; 2] {
-        [
-            ProviderSpec {
-                provider: Provider::Anthropic,
-                key: self.anthropic_admin_api_key.clone(),
-                missing_key_error: Error::AnthropicKeyNotFound,
-            },
-            ProviderSpec {
-                provider: Provider::Openai,
-                key: self.openai_admin_api_key.clone(),
-                missing_key_error: Error::OpenaiKeyNotFound,
-            },
-        ]
+        Ok(time_zone)
     }
 }
 
@@ -139,30 +63,63 @@ pub struct Cli {
     #[serde(skip)] // ttl is just for querying the cache, so keep it away from the cache key.
     pub ttl_minutes: i64,
 
-    /// Only support day for now, for example '2d'.
+    /// Return an expired result right away, marked with '⟳', and refresh it in the background.
+    /// Keeps status bars from freezing while the API is paged through.
+    #[arg(long, default_value_t = false, global = true)]
+    #[serde(skip)] // Same answer either way, only sooner.
+    pub stale_ok: bool,
+
+    /// Show what the providers that answered add up to, even if others failed.
+    /// The missing ones are marked with '⚠', and it exits with 3 instead of 0.
+    #[arg(long, default_value_t = false, global = true)]
+    // Part of the cache key, partial answers are cached too, and a run without it must not get one.
+    pub partial: bool,
+
+    /// How many times to try a request on rate limits, server and connection errors.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    #[serde(skip)] // How hard we try doesn't change the answer.
+    pub max_attempts: u32,
+
+    /// Where the report starts: '12h', '2d', '2w', '1mo' back from now,
+    /// or a date like '2025-01-31', or an RFC 3339 timestamp.
+    /// Days, weeks and months start at midnight.
     #[arg(long, default_value = "0d", global = true)]
     pub since: String,
 
-    #[arg(
-        long,
-        env = "ANTHROPIC_ADMIN_API_KEY",
-        hide_env_values = true,
-        global = true
-    )]
-    pub anthropic_admin_api_key: Option<String>,
+    /// Optional. Where the report ends (exclusive), same format as --since.
+    /// Defaults to now.
+    #[arg(long, global = true)]
+    pub until: Option<String>,
 
-    #[arg(
-        long,
-        env = "OPENAI_ADMIN_API_KEY",
-        hide_env_values = true,
-        global = true
-    )]
-    pub openai_admin_api_key: Option<String>,
+    /// Optional. The timezone for midnights, periods and time groups,
+    /// for example 'America/Los_Angeles'. Defaults to the system timezone.
+    /// Usage comes in whole UTC hours. Where the offset isn't, like 'Asia/Kolkata', a report
+    /// starts with the hour its start falls in, so a day begins with the last half hour before it.
+    #[arg(long, env = "METER_TZ", global = true)]
+    pub tz: Option<String>,
+
+    /// Optional. A named calendar period, instead of --since and --until.
+    #[arg(long, global = true, conflicts_with_all = ["since", "until"])]
+    pub period: Option<Period>,
+
+    /// A TOML or JSON pricing file that overrides or extends the built-in prices.
+    /// Defaults to `pricing.toml` or `pricing.json` in `{config_dir}/meter/`.
+    #[arg(long, global = true)]
+    pub pricing_file: Option<std::path::PathBuf>,
+
+    // The keys and base URLs, every source brings its own.
+    #[command(flatten)]
+    #[serde(flatten)]
+    pub anthropic: AnthropicArgs,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    pub openai: OpenaiArgs,
 
     // #[serde(skip)]
     // Decided to include this key in the command signature itself to ensure integrity
     // if the user has multiple keys on the same machine.
-    /// Provider to use. Currently only supports 'anthropic'.
+    /// Providers to use, for example 'anthropic,openai'. Defaults to every provider with a key.
     #[arg(
         long,
         value_delimiter = ',',
@@ -184,6 +141,9 @@ pub enum Commands {
     ///
     /// Go build something fun on top of this!
     Raw,
+
+    /// Inspect and purge the cached results.
+    Cache(CacheArgs),
 }
 
 #[derive(clap::Args, Debug, Serialize)]
@@ -193,8 +153,57 @@ pub struct SumArgs {
     pub metric: Metric,
 
     /// Optional. How to group results.
-    #[arg(long)]
-    pub group_by: Option<Grouping>,
+    /// Takes an ordered list for nested groups, for example 'workspace,model'.
+    #[arg(long, value_delimiter = ',')]
+    pub group_by: Option<Vec<Grouping>>,
+
+    /// What to do with models that have no price: 'error', 'skip' them altogether,
+    /// count them at 'zero' cost, or price them like another, 'fallback=claude-sonnet-4-5'.
+    /// Unpriced models are listed under the cost. Tokens are always counted, except with 'skip'.
+    #[arg(long, default_value = "zero", value_parser = parse_unknown_model_policy)]
+    pub on_unknown_model: UnknownModelPolicy,
+}
+
+#[derive(clap::Args, Debug, Serialize)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+}
+
+#[derive(Subcommand, Debug, Serialize)]
+pub enum CacheAction {
+    /// List the cached results, newest first, with the command that created each one.
+    /// Then the usage stores they are computed from.
+    List,
+
+    /// Print one cached result and where it came from.
+    Show {
+        /// The hash from `meter cache list`, for example '7a2f4c91b0e3'.
+        hash: String,
+    },
+
+    /// Remove cached results, and the usage stores.
+    Clear {
+        /// Optional. Only remove results created, and stores last written, before this point.
+        /// Same format as --since.
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+
+    /// Print the directory everything is cached in.
+    Path,
+}
+
+impl CacheAction {
+    /// Same rules as `--since`, see `TimeBound`.
+    pub fn try_parse_older_than(&self) -> AppResult<Option<TimeBound>> {
+        match self {
+            CacheAction::Clear {
+                older_than: Some(older_than),
+            } => parse_time_bound(older_than).map(Some),
+            _ => Ok(None),
+        }
+    }
 }
 
 #[derive(Serialize, ValueEnum, Clone, Debug, Default)]
@@ -205,12 +214,52 @@ pub enum Metric {
     Tokens,
 }
 
+/// See `SumArgs::on_unknown_model`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnknownModelPolicy {
+    Error,
+    Skip,
+    Zero,
+    /// The name of a model in the pricing table.
+    Fallback(String),
+}
+
 #[derive(Serialize, ValueEnum, Clone, Debug, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum Grouping {
     #[default]
     Model,
-    // Provider, // No, for now.
+    Provider,
+    /// Anthropic workspace, or OpenAI project.
+    Workspace,
+    ApiKey,
+    ServiceTier,
+    ContextWindow,
+    /// Calendar periods, in the --tz timezone.
+    /// Hours are labelled by their first minute, '23:30' where the offset is half an hour.
+    Hour,
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+}
+
+#[derive(Serialize, ValueEnum, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Period {
+    Today,
+    Yesterday,
+    /// Since Monday.
+    ThisWeek,
+    /// Monday to Sunday of the week before.
+    LastWeek,
+    ThisMonth,
+    LastMonth,
+    /// Month to date, same as this-month.
+    Mtd,
+    /// Year to date.
+    Ytd,
 }
 
 #[derive(Clone, Debug, Serialize, ValueEnum, Deserialize, PartialEq, Eq, Hash)]
@@ -220,16 +269,78 @@ pub enum Provider {
     Openai,
 }
 
-/// This is a blueprint for each provider to run the load_providers function.
-/// The function runs this vector and checks if the key exists in the system.
-/// If not, it raises the associated error.
-/// This struct is not used in the main application logic.
-pub struct ProviderSpec {
-    pub provider: Provider,
-    pub key: Option<String>,
-    pub missing_key_error: Error,
+impl std::fmt::Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Provider::Anthropic => "anthropic",
+            Provider::Openai => "openai",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// One end of the report range, as the user typed it.
+/// Relative ones still need "now" to become a real time, see `resolve_time_bound` in main.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeBound {
+    /// '12h', back from now, from the start of that hour.
+    HoursAgo(i64),
+    /// '2d', from midnight.
+    DaysAgo(i64),
+    /// '2w', from midnight.
+    WeeksAgo(i64),
+    /// '1mo', from midnight.
+    MonthsAgo(i64),
+    /// '2025-01-31', from midnight.
+    Date(jiff::civil::Date),
+    /// '2025-01-31T12:00:00Z', exactly.
+    Timestamp(jiff::Timestamp),
+}
+
+/// Parses a time bound, either relative ('12h', '2d', '2w', '1mo'), or absolute
+/// ('2025-01-31', '2025-01-31T12:00:00Z').
+fn parse_time_bound(input: &str) -> AppResult<TimeBound> {
+    // Absolute ones first, so a date doesn't get mistaken for a number of days.
+    if let Ok(timestamp) = input.parse::<jiff::Timestamp>() {
+        return Ok(TimeBound::Timestamp(timestamp));
+    }
+
+    if let Ok(date) = input.parse::<jiff::civil::Date>() {
+        return Ok(TimeBound::Date(date));
+    }
+
+    let (digits, into_bound): (&str, fn(i64) -> TimeBound) =
+        if let Some(digits) = input.strip_suffix("mo") {
+            (digits, TimeBound::MonthsAgo)
+        } else if let Some(digits) = input.strip_suffix('h') {
+            (digits, TimeBound::HoursAgo)
+        } else if let Some(digits) = input.strip_suffix('d') {
+            (digits, TimeBound::DaysAgo)
+        } else if let Some(digits) = input.strip_suffix('w') {
+            (digits, TimeBound::WeeksAgo)
+        } else {
+            let error = Error::UnsupportedTimeUnit(input.to_owned());
+
+            return Err(error.into());
+        };
+
+    let numbers = digits
+        .parse::<u32>()
+        .map_err(|_| Error::InvalidDuration(digits.to_owned()))?;
+
+    Ok(into_bound(i64::from(numbers)))
 }
 
-/// a simple pair representing a validated provider and its required api key.
-/// This is used to control the main logic, determining which provider to dispatch fetch to.
-pub type ProviderKeyPair = (Provider, String);
+/// Parses `error`, `skip`, `zero` or `fallback=<model>`.
+fn parse_unknown_model_policy(input: &str) -> Result<UnknownModelPolicy, String> {
+    match input.split_once('=') {
+        Some(("fallback", model)) if !model.is_empty() => {
+            Ok(UnknownModelPolicy::Fallback(model.to_owned()))
+        }
+        None if input == "error" => Ok(UnknownModelPolicy::Error),
+        None if input == "skip" => Ok(UnknownModelPolicy::Skip),
+        None if input == "zero" => Ok(UnknownModelPolicy::Zero),
+        _ => Err("expected 'error', 'skip', 'zero' or 'fallback=<model>'".to_owned()),
+    }
+}
@@ -1 +1,2 @@
+pub mod pricing_file;
 pub mod pricing_table;
@@ -0,0 +1,119 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use miette::{NamedSource, SourceOffset, SourceSpan};
+
+use super::pricing_table::{PRICING, PricingTable};
+use crate::error::Error;
+use crate::prelude::*;
+
+/// File names looked up in `{config_dir}/meter/` when no `--pricing-file` is given.
+/// The first one that exists wins.
+const DEFAULT_FILE_NAMES: [&str; 2] = ["pricing.toml", "pricing.json"];
+
+/// The shape of a pricing file. Same rows as the built-in table.
+///
+/// ```toml
+/// [[pricing]]
+/// base_model_name = "claude-opus-4-6"
+/// context_window = "0-200k"
+/// input_multiplier = 5.0
+/// output_multiplier = 25.0
+/// cache_read_multiplier = 0.5
+/// cache_write_5m_multiplier = 6.25
+/// cache_write_1h_multiplier = 10.0
+///
+/// # Optional, on top of the half-price batches every model gets.
+/// service_tiers = [{ name = "priority", multiplier = 1.8 }]
+///
+/// # Optional, for a price that changed. UTC days, the until one isn't included.
+/// effective_from = "2025-11-24"
+/// effective_until = "2026-03-01"
+/// ```
+#[derive(Debug, Deserialize)]
+struct PricingFile {
+    pricing: Vec<PricingTable>,
+}
+
+/// Builds the pricing table the app runs with.
+///
+/// Starts from the built-in `PRICING`, then applies the user's pricing file on top of it, if
+/// there is one. A row with the same model name, context window and effective dates replaces
+/// the built-in one, anything else is added, after it. Where an added row overlaps a built-in
+/// one in time, the added one wins.
+///
+/// An explicit `pricing_file` has to exist, the default location is optional.
+pub fn try_load_pricing(pricing_file: Option<&Path>) -> AppResult<Vec<PricingTable>> {
+    let mut table = PRICING.to_vec();
+
+    let file_path = match pricing_file {
+        Some(path) => Some(path.to_path_buf()),
+        None => find_default_pricing_file(),
+    };
+
+    let Some(file_path) = file_path else {
+        return Ok(table);
+    };
+
+    for user_row in try_read_pricing_file(&file_path)? {
+        let existing_row = table.iter_mut().find(|row| {
+            row.base_model_name == user_row.base_model_name
+                && row.context_window == user_row.context_window
+                && row.effective_from == user_row.effective_from
+                && row.effective_until == user_row.effective_until
+        });
+
+        match existing_row {
+            Some(row) => *row = user_row,
+            None => table.push(user_row),
+        }
+    }
+
+    Ok(table)
+}
+
+/// `{config_dir}/meter/pricing.toml`, or `.json`, if either of them exists.
+fn find_default_pricing_file() -> Option<PathBuf> {
+    let dir = dirs::config_dir()?.join("meter");
+
+    DEFAULT_FILE_NAMES
+        .iter()
+        .map(|file_name| dir.join(file_name))
+        .find(|path| path.is_file())
+}
+
+/// Reads and parses a pricing file. The format is picked by its extension, TOML by default.
+fn try_read_pricing_file(file_path: &Path) -> AppResult<Vec<PricingTable>> {
+    let content = fs::read_to_string(file_path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Could not read the pricing file at {}", file_path.display()))?;
+
+    let is_json = file_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+    let parsed = if is_json {
+        serde_json::from_str::<PricingFile>(&content).map_err(|e| {
+            // serde_json only knows lines and columns, miette wants an offset.
+            let offset = SourceOffset::from_location(&content, e.line(), e.column());
+
+            (e.to_string(), SourceSpan::new(offset, 1))
+        })
+    } else {
+        toml::from_str::<PricingFile>(&content).map_err(|e| {
+            let span = e.span().unwrap_or(0..0);
+
+            (e.message().to_owned(), span.into())
+        })
+    };
+
+    match parsed {
+        Ok(pricing_file) => Ok(pricing_file.pricing),
+        Err((reason, span)) => Err(Error::PricingFileMalformed {
+            src: NamedSource::new(file_path.display().to_string(), content),
+            span,
+            reason,
+        }
+        .into()),
+    }
+}
@@ -1,48 +1,374 @@
 #![allow(dead_code)] // To silence the compiler warnings.
 
-#[derive(Debug)]
+use std::borrow::Cow;
+
+use jiff::civil::{Date, date};
+
+use crate::prelude::*;
+
+/// Context window value for pricing rows that apply to any context window.
+pub const ANY_CONTEXT_WINDOW: &str = "all";
+
+/// A single pricing row.
+///
+/// The names are `Cow` so the built-in rows can stay static,
+/// while rows from the user's pricing file can own their strings.
+///
+/// A model whose price changed has a row per price, each in force for its own stretch of time,
+/// so old usage keeps the price it was billed at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PricingTable {
-    pub base_model_name: &'static str,
-    pub context_window: &'static str,
+    pub base_model_name: Cow<'static, str>,
+    #[serde(default = "any_context_window")]
+    pub context_window: Cow<'static, str>,
+    /// Price per million uncached input tokens.
     pub input_multiplier: f64,
+    /// Price per million output tokens.
     pub output_multiplier: f64,
+    /// Price per million input tokens read from the cache.
+    pub cache_read_multiplier: f64,
+    /// Price per million input tokens written to a 5-minute cache entry.
+    pub cache_write_5m_multiplier: f64,
+    /// Price per million input tokens written to a 1-hour cache entry.
+    pub cache_write_1h_multiplier: f64,
+    /// Service tiers this model is priced differently in, on top of `DEFAULT_SERVICE_TIERS`.
+    #[serde(default)]
+    pub service_tiers: Cow<'static, [ServiceTierRate]>,
+    /// The first day this price applies, in UTC. `None` means since forever.
+    #[serde(default)]
+    pub effective_from: Option<Date>,
+    /// The day this price stopped applying, in UTC, not included. `None` means it still does.
+    #[serde(default)]
+    pub effective_until: Option<Date>,
+}
+
+/// What a service tier costs, compared to the rates of its row.
+///
+/// Applies to every kind of token alike, which is how both providers discount batches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceTierRate {
+    /// As the API reports it, e.g. "batch", "flex" or "priority".
+    pub name: Cow<'static, str>,
+    pub multiplier: f64,
+}
+
+/// The tiers that cost the same for every model, unless a row says otherwise.
+/// Any tier that is in neither list is billed at the standard rates, like "standard" or "default".
+pub static DEFAULT_SERVICE_TIERS: &[ServiceTierRate] = &[
+    // Batches are half price on both providers.
+    ServiceTierRate {
+        name: Cow::Borrowed("batch"),
+        multiplier: 0.5,
+    },
+    // OpenAI's flex processing is billed like a batch.
+    ServiceTierRate {
+        name: Cow::Borrowed("flex"),
+        multiplier: 0.5,
+    },
+];
+
+impl PricingTable {
+    /// Whether this price applied on `day`, the UTC day a usage bucket starts.
+    pub fn is_in_force(&self, day: Date) -> bool {
+        self.effective_from.is_none_or(|from| from <= day)
+            && self.effective_until.is_none_or(|until| day < until)
+    }
+
+    /// The stretch of time this price applies to, e.g. "2025-06-10.." or "..2025-06-10".
+    pub fn effective_period(&self) -> String {
+        let format = |day: Option<Date>| day.map(|day| day.to_string()).unwrap_or_default();
+
+        match (self.effective_from, self.effective_until) {
+            (None, None) => "always".to_owned(),
+            (from, until) => format!("{}..{}", format(from), format(until)),
+        }
+    }
+
+    /// How much a usage entry in `service_tier` costs, compared to the rates of this row.
+    ///
+    /// The row's own tiers first, then the defaults. Anything else is the standard tier.
+    pub fn service_tier_multiplier(&self, service_tier: Option<&str>) -> f64 {
+        let Some(service_tier) = service_tier else {
+            return 1.0;
+        };
+
+        self.service_tiers
+            .iter()
+            .chain(DEFAULT_SERVICE_TIERS)
+            .find(|rate| rate.name == service_tier)
+            .map_or(1.0, |rate| rate.multiplier)
+    }
 }
 
 pub static PRICING: &[PricingTable] = &[
+    // Anthropic.
+    //
+    // Cache reads are 0.1x the input price, 5-minute writes are 1.25x, and 1-hour writes are 2x.
+    // Priority Tier is paid for by commitment, its tokens cost the standard rates.
     PricingTable {
-        base_model_name: "claude-haiku-4-5",
-        context_window: "0-200k",
+        base_model_name: Cow::Borrowed("claude-haiku-4-5"),
+        context_window: Cow::Borrowed("0-200k"),
         input_multiplier: 1.0,
         output_multiplier: 5.0,
+        cache_read_multiplier: 0.1,
+        cache_write_5m_multiplier: 1.25,
+        cache_write_1h_multiplier: 2.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
     },
     PricingTable {
-        base_model_name: "claude-sonnet-4-5",
-        context_window: "0-200k",
+        base_model_name: Cow::Borrowed("claude-sonnet-4-5"),
+        context_window: Cow::Borrowed("0-200k"),
         input_multiplier: 3.0,
         output_multiplier: 15.0,
+        cache_read_multiplier: 0.3,
+        cache_write_5m_multiplier: 3.75,
+        cache_write_1h_multiplier: 6.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
     },
     PricingTable {
-        base_model_name: "claude-sonnet-4-5",
-        context_window: "200k-1M",
+        base_model_name: Cow::Borrowed("claude-sonnet-4-5"),
+        context_window: Cow::Borrowed("200k-1M"),
         input_multiplier: 6.0,
         output_multiplier: 22.5,
+        cache_read_multiplier: 0.6,
+        cache_write_5m_multiplier: 7.5,
+        cache_write_1h_multiplier: 12.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
     },
     PricingTable {
-        base_model_name: "claude-sonnet-4",
-        context_window: "0-200k",
+        base_model_name: Cow::Borrowed("claude-sonnet-4"),
+        context_window: Cow::Borrowed("0-200k"),
         input_multiplier: 3.0,
         output_multiplier: 15.0,
+        cache_read_multiplier: 0.3,
+        cache_write_5m_multiplier: 3.75,
+        cache_write_1h_multiplier: 6.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
     },
     PricingTable {
-        base_model_name: "claude-sonnet-4",
-        context_window: "200k-1M",
+        base_model_name: Cow::Borrowed("claude-sonnet-4"),
+        context_window: Cow::Borrowed("200k-1M"),
         input_multiplier: 6.0,
         output_multiplier: 22.5,
+        cache_read_multiplier: 0.6,
+        cache_write_5m_multiplier: 7.5,
+        cache_write_1h_multiplier: 12.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
     },
     PricingTable {
-        base_model_name: "claude-opus-4-5",
-        context_window: "0-200k", // Claude doesn't have long context pricing for this model.
+        base_model_name: Cow::Borrowed("claude-opus-4-5"),
+        context_window: Cow::Borrowed("0-200k"), // Claude doesn't have long context pricing for this model.
         input_multiplier: 5.0,
         output_multiplier: 25.0,
+        cache_read_multiplier: 0.5,
+        cache_write_5m_multiplier: 6.25,
+        cache_write_1h_multiplier: 10.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
+    },
+    // OpenAI.
+    //
+    // OpenAI doesn't price by context window, so these rows use "all".
+    // It doesn't charge for cache writes either, so they are billed as plain input.
+    // Priority processing costs more, by a different factor for every model.
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-5-nano"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 0.05,
+        output_multiplier: 0.4,
+        cache_read_multiplier: 0.005,
+        cache_write_5m_multiplier: 0.05,
+        cache_write_1h_multiplier: 0.05,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-5-mini"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 0.25,
+        output_multiplier: 2.0,
+        cache_read_multiplier: 0.025,
+        cache_write_5m_multiplier: 0.25,
+        cache_write_1h_multiplier: 0.25,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.8,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-5"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 1.25,
+        output_multiplier: 10.0,
+        cache_read_multiplier: 0.125,
+        cache_write_5m_multiplier: 1.25,
+        cache_write_1h_multiplier: 1.25,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 2.0,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4.1-nano"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 0.1,
+        output_multiplier: 0.4,
+        cache_read_multiplier: 0.025,
+        cache_write_5m_multiplier: 0.1,
+        cache_write_1h_multiplier: 0.1,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 2.0,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4.1-mini"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 0.4,
+        output_multiplier: 1.6,
+        cache_read_multiplier: 0.1,
+        cache_write_5m_multiplier: 0.4,
+        cache_write_1h_multiplier: 0.4,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.75,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4.1"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 2.0,
+        output_multiplier: 8.0,
+        cache_read_multiplier: 0.5,
+        cache_write_5m_multiplier: 2.0,
+        cache_write_1h_multiplier: 2.0,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.75,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4o-mini"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 0.15,
+        output_multiplier: 0.6,
+        cache_read_multiplier: 0.075,
+        cache_write_5m_multiplier: 0.15,
+        cache_write_1h_multiplier: 0.15,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.67,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    // The first gpt-4o snapshot, still priced like at launch.
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4o-2024-05-13"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 5.0,
+        output_multiplier: 15.0,
+        cache_read_multiplier: 5.0,
+        cache_write_5m_multiplier: 5.0,
+        cache_write_1h_multiplier: 5.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("gpt-4o"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 2.5,
+        output_multiplier: 10.0,
+        cache_read_multiplier: 1.25,
+        cache_write_5m_multiplier: 2.5,
+        cache_write_1h_multiplier: 2.5,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.7,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("o4-mini"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 1.1,
+        output_multiplier: 4.4,
+        cache_read_multiplier: 0.275,
+        cache_write_5m_multiplier: 1.1,
+        cache_write_1h_multiplier: 1.1,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.82,
+        }]),
+        effective_from: None,
+        effective_until: None,
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("o3-mini"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 1.1,
+        output_multiplier: 4.4,
+        cache_read_multiplier: 0.55,
+        cache_write_5m_multiplier: 1.1,
+        cache_write_1h_multiplier: 1.1,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: None,
+    },
+    // o3 got 80% cheaper on 2025-06-10.
+    PricingTable {
+        base_model_name: Cow::Borrowed("o3"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 10.0,
+        output_multiplier: 40.0,
+        cache_read_multiplier: 2.5,
+        cache_write_5m_multiplier: 10.0,
+        cache_write_1h_multiplier: 10.0,
+        service_tiers: Cow::Borrowed(&[]),
+        effective_from: None,
+        effective_until: Some(date(2025, 6, 10)),
+    },
+    PricingTable {
+        base_model_name: Cow::Borrowed("o3"),
+        context_window: Cow::Borrowed("all"),
+        input_multiplier: 2.0,
+        output_multiplier: 8.0,
+        cache_read_multiplier: 0.5,
+        cache_write_5m_multiplier: 2.0,
+        cache_write_1h_multiplier: 2.0,
+        service_tiers: Cow::Borrowed(&[ServiceTierRate {
+            name: Cow::Borrowed("priority"),
+            multiplier: 1.75,
+        }]),
+        effective_from: Some(date(2025, 6, 10)),
+        effective_until: None,
     },
 ];
+
+fn any_context_window() -> Cow<'static, str> {
+    Cow::Borrowed(ANY_CONTEXT_WINDOW)
+}
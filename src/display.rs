@@ -7,6 +7,10 @@ use std::sync::Mutex;
 pub struct Display {
     pub spinner: Mutex<SpinnerContainer>,
     no_animate: bool,
+
+    /// The latest message of every provider being fetched, in the order they started.
+    /// They share one spinner, so it shows all of them side by side.
+    progress: Mutex<Vec<(String, String)>>,
 }
 
 impl Display {
@@ -14,6 +18,7 @@ impl Display {
         Self {
             no_animate,
             spinner: Mutex::new(SpinnerContainer::new()),
+            progress: Mutex::new(vec![]),
         }
     }
 
@@ -34,6 +39,21 @@ impl Display {
     pub fn update_spin_message(&self, message: String) {
         self.spinner.lock().unwrap().update_text(message);
     }
+
+    /// Replaces the message of one provider, and keeps the others.
+    /// Safe to call from the threads fetching in parallel.
+    pub fn update_progress(&self, source: &str, message: String) {
+        let mut progress = self.progress.lock().unwrap();
+
+        match progress.iter_mut().find(|(name, _)| name == source) {
+            Some((_, current)) => *current = message,
+            None => progress.push((source.to_owned(), message)),
+        }
+
+        let combined = progress.iter().map(|(_, message)| message.as_str());
+
+        self.update_spin_message(combined.collect::<Vec<_>>().join(" · "));
+    }
 }
 
 // End of Display
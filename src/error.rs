@@ -1,56 +1,62 @@
-use miette::Diagnostic;
+use miette::{Diagnostic, NamedSource, SourceSpan};
 use thiserror::Error;
 
 // My ideas are...
 // meter::parse -> cli argument parsing/validation.
 // meter::config -> environment, credentials, settings.
-// meter::api -> http/provider errors (future).
-// meter::api::request -> for example.
+// meter::api -> http/provider errors.
+// meter::api::unauthorized -> for example.
 // meter::internal -> "This thing shouldn't happen" errors (future).
 // meter::internal::unexpected -> for example.
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum Error {
-    #[error("Invalid duration format: expected integer before unit, got '{0}'")]
+    #[error("Invalid duration: expected a reasonable integer before unit, got '{0}'")]
     #[diagnostic(
         code(meter::parse::duration),
-        help("Please provide a valid integer, like '1' or '30'.")
+        help("Please provide a valid integer, like '1' or '30', that doesn't go back centuries.")
     )]
     InvalidDuration(String),
 
-    #[error(
-        "Unsupported time unit. Currently only 'd' (days) suffix (e.g., '1d') is supported, got '{0}'."
-    )]
+    #[error("Unsupported time format, got '{0}'.")]
     #[diagnostic(
         code(meter::parse::time_unit),
-        help("Try using 'd' for days, example: '2d'.")
+        help(
+            "Try 'h' for hours, 'd' for days, 'w' for weeks or 'mo' for months, example: '2d'.\n\
+Or a date like '2025-01-31', or an RFC 3339 timestamp like '2025-01-31T12:00:00Z'."
+        )
     )]
     UnsupportedTimeUnit(String),
 
-    /// Kaboom
-    #[error("Anthropic API key not found.")]
+    #[error("Unknown timezone '{0}'.")]
     #[diagnostic(
-        code(meter::config::api_key),
-        help(
-"Ensure that the ANTHROPIC_ADMIN_API_KEY environment variable is set with your API key.\n\
-Try running `echo $ANTHROPIC_ADMIN_API_KEY` to check if it's present or restart your shell.
-            "
-        ),
-        url("https://platform.claude.com/docs/en/build-with-claude/administration-api")
+        code(meter::parse::time_zone),
+        help("Use an IANA timezone name, like 'America/Los_Angeles' or 'UTC'.")
     )]
-    AnthropicKeyNotFound,
+    UnknownTimeZone(String),
 
-    #[error("OpenAI API key not found.")]
+    #[error("The report range is empty: it starts at {start} but ends at {end}.")]
+    #[diagnostic(
+        code(meter::parse::time_range),
+        help("Make sure --until comes after --since.")
+    )]
+    EmptyTimeRange { start: String, end: String },
+
+    /// Kaboom
+    #[error("{provider} API key not found.")]
     #[diagnostic(
         code(meter::config::api_key),
         help(
-"Ensure that the ANTHROPIC_ADMIN_API_KEY environment variable is set with your API key.\n\
-Try running `echo $ANTHROPIC_ADMIN_API_KEY` to check if it's present or restart your shell.
-            "
+            "Ensure that the {env_var} environment variable is set with your admin key.\n\
+Try running `echo ${env_var}` to check if it's present or restart your shell."
         ),
-        url("https://platform.claude.com/docs/en/build-with-claude/administration-api")
+        url("{docs_url}")
     )]
-    OpenaiKeyNotFound,
+    ApiKeyNotFound {
+        provider: String,
+        env_var: String,
+        docs_url: String,
+    },
 
     /// I am too lazy to add every model to the table, so this is the price for users.
     /// This will take them to the GitHub issue form, prefilled.
@@ -60,7 +66,10 @@ Try running `echo $ANTHROPIC_ADMIN_API_KEY` to check if it's present or restart
     )]
     #[diagnostic(
         code(meter::pricing::missing_configuration),
-        help("Please report this using the URL above so we can add the pricing. Thank you!"),
+        help(
+            "Please report this using the URL above so we can add the pricing. Thank you!\n\
+Meanwhile, --on-unknown-model zero, skip or fallback=<model> still gets you a number."
+        ),
         url(
             "https://github.com/lngsx/meter/issues/new?title=%F0%9F%92%B8%20Missing%20pricing%20configuration&body=model: {model:?}%0Acontext_window: {context_window:?}"
         )
@@ -70,11 +79,245 @@ Try running `echo $ANTHROPIC_ADMIN_API_KEY` to check if it's present or restart
         context_window: String,
     },
 
-    #[error("Rate limit exceeded (HTTP 429). The API is asking us to slow down.")]
+    /// The model is in the table, but not for this context window.
+    /// Billing it at another tier would silently give the wrong number.
+    #[error(
+        "🙏 Sorry! The system is fine, but pricing information is missing for this context window:\nmodel: {model:?}\ncontext_window: {context_window:?}\nknown context windows: {known_context_windows}"
+    )]
+    #[diagnostic(
+        code(meter::pricing::missing_context_window),
+        help("Please report this using the URL above so we can add the pricing tier. Thank you!"),
+        url(
+            "https://github.com/lngsx/meter/issues/new?title=%F0%9F%92%B8%20Missing%20pricing%20tier&body=model: {model:?}%0Acontext_window: {context_window:?}"
+        )
+    )]
+    PricingTierNotFound {
+        model: String,
+        context_window: String,
+        known_context_windows: String,
+    },
+
+    /// The model and window are in the table, but none of their prices applied back then.
+    /// Usually a pricing file row that starts too late.
+    #[error(
+        "There is no price for {model:?} ({context_window}) on {day}.\nknown periods: {known_periods}"
+    )]
+    #[diagnostic(
+        code(meter::pricing::missing_period),
+        help(
+            "Check the effective_from and effective_until of its rows in your pricing file.\n\
+Dates are in UTC, effective_until is not included."
+        )
+    )]
+    PricingPeriodNotFound {
+        model: String,
+        context_window: String,
+        day: String,
+        known_periods: String,
+    },
+
+    #[error("There is no model '{0}' in the pricing table to fall back to.")]
+    #[diagnostic(
+        code(meter::parse::fallback_model),
+        help(
+            "Use a base_model_name from the pricing table, like 'fallback=claude-sonnet-4-5'.\n\
+The full name the API reports, with its date, won't match."
+        )
+    )]
+    FallbackModelNotFound(String),
+
+    #[error("The pricing file is malformed: {reason}")]
+    #[diagnostic(
+        code(meter::config::pricing_file),
+        help(
+            "Each [[pricing]] row needs base_model_name, the five *_multiplier prices, and optionally a context_window."
+        )
+    )]
+    PricingFileMalformed {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("{reason}")]
+        span: SourceSpan,
+        reason: String,
+    },
+
+    #[error("Another meter is still fetching the same data, and there is no older result to show.")]
+    #[diagnostic(
+        code(meter::cache::lock_timeout),
+        help("Give it a moment, then run the command again.")
+    )]
+    CacheLockTimeout,
+
+    /// The data store of a provider, shared by every command, not the output cache.
+    #[error("Another meter is still fetching the {provider} usage, and it's taking too long.")]
+    #[diagnostic(
+        code(meter::cache::store_lock_timeout),
+        help(
+            "Give it a moment, then run the command again.\n\
+If no other meter is running, `meter cache clear` removes the stores, and their locks."
+        )
+    )]
+    StoreLockTimeout { provider: String },
+
+    /// Mostly a regular API key where an admin one is needed, they look alike.
+    #[error("{provider} rejected the API key (HTTP 401).\nresponse: {body}")]
+    #[diagnostic(
+        code(meter::api::unauthorized),
+        help(
+            "The usage report needs an admin key, a regular API key won't do.\n\
+Anthropic admin keys start with 'sk-ant-admin', OpenAI ones are under the organization settings.\n\
+Also make sure the key wasn't revoked."
+        )
+    )]
+    ApiUnauthorized { provider: String, body: String },
+
+    #[error("{provider} refused access to the usage report (HTTP 403).\nresponse: {body}")]
+    #[diagnostic(
+        code(meter::api::forbidden),
+        help(
+            "The key is fine, but the organization can't use the usage report.\n\
+It's only available to organizations, not individual accounts, and only to admins."
+        )
+    )]
+    ApiForbidden { provider: String, body: String },
+
+    #[error("{provider} has no usage report at {endpoint} (HTTP 404).\nresponse: {body}")]
+    #[diagnostic(
+        code(meter::api::not_found),
+        help(
+            "If you changed the base URL, check it. The Anthropic one has no '/v1' at the end, \
+the OpenAI one does.\nOtherwise the endpoint may have moved, please let us know."
+        )
+    )]
+    ApiEndpointNotFound {
+        provider: String,
+        endpoint: String,
+        body: String,
+    },
+
+    #[error("{provider} answered with HTTP {status}.\nresponse: {body}")]
+    #[diagnostic(
+        code(meter::api::unexpected_status),
+        help("It may be a hiccup on their side. Try again in a few minutes.")
+    )]
+    ApiUnexpectedStatus {
+        provider: String,
+        status: u16,
+        body: String,
+    },
+
+    #[error("{provider} took too long to answer.")]
+    #[diagnostic(
+        code(meter::api::timeout),
+        help("The API or the network is slow right now. Try again in a few minutes.")
+    )]
+    ApiTimeout { provider: String },
+
+    #[error("Could not find the host of {endpoint}.")]
+    #[diagnostic(
+        code(meter::api::dns),
+        help("Check the network connection, and the base URL if you changed it.")
+    )]
+    ApiHostNotFound { endpoint: String },
+
+    #[error("Could not reach {provider}: {reason}")]
+    #[diagnostic(
+        code(meter::api::unreachable),
+        help("Check the network connection, or any proxy in between.")
+    )]
+    ApiUnreachable { provider: String, reason: String },
+
+    /// The API changed under us, or something in between answers instead of it.
+    #[error(
+        "🙏 Sorry! {provider} answered, but not in the shape we expected: {reason}\nresponse: {body}"
+    )]
+    #[diagnostic(
+        code(meter::api::unexpected_schema),
+        help("Please report this using the URL above so we can catch up. Thank you!"),
+        url(
+            "https://github.com/lngsx/meter/issues/new?title=%F0%9F%A7%A9%20Unexpected%20API%20response&body=provider: {provider}%0Areason: {reason}"
+        )
+    )]
+    ApiUnexpectedSchema {
+        provider: String,
+        reason: String,
+        body: String,
+    },
+
+    /// Providers are fetched side by side, and one of them falling over shouldn't hide why
+    /// the others did too.
+    #[error("Could not fetch from {failed}.")]
+    #[diagnostic(code(meter::api::source_failed))]
+    SourcesFailed {
+        failed: String,
+        #[help]
+        advice: String,
+        #[related]
+        errors: Vec<RelatedReport>,
+    },
+
+    #[error("Rate limit exceeded (HTTP 429). The {provider} API is asking us to slow down.")]
     #[diagnostic(
         code(meter::api::rate_limit),
         help("We hit the limit. Try running the command again in a few minutes."),
-        url("https://platform.claude.com/docs/en/api/rate-limits")
+        url("{docs_url}")
     )]
-    AnthropicRateLimitExceeded,
+    ApiRateLimited { provider: String, docs_url: String },
+}
+
+/// A report that can go into `#[related]`, which miette's own `Report` can't.
+/// Every part of the diagnostic is the wrapped one's, so it renders the same.
+#[derive(Debug)]
+pub struct RelatedReport(pub miette::Report);
+
+impl From<miette::Report> for RelatedReport {
+    fn from(report: miette::Report) -> Self {
+        RelatedReport(report)
+    }
+}
+
+impl std::fmt::Display for RelatedReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for RelatedReport {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl Diagnostic for RelatedReport {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.0.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.0.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.0.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.0.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.0.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.0.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.0.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.0.diagnostic_source()
+    }
 }
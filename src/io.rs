@@ -1,3 +1,8 @@
+pub mod bucket_store;
 pub mod cache;
 pub mod claude_client;
+pub mod openai_client;
+pub mod response;
+pub mod retry;
+pub mod source;
 pub mod unified_dtos;
@@ -0,0 +1,494 @@
+//! The data-level cache.
+//!
+//! Keeps the unified buckets of one provider, before any calculation, so every report,
+//! grouping and format can be computed locally from the same API calls.
+//! It sits under the output cache, which still answers repeated commands first.
+//!
+//! Hourly buckets that are over (closed) never change, so the store remembers which stretch
+//! of time it already holds and only asks the API for what's missing, which is usually just
+//! the last incomplete bucket up to now.
+//!
+//! ```text
+//! covered_from                        covered_until        now
+//! |--------- closed buckets ----------|---- open buckets ---|
+//!                                     ^ the next fetch starts here
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use jiff::Timestamp;
+
+use super::cache::{
+    generate_cache_filename, meter_cache_dir, try_acquire_lock, try_write_atomically,
+};
+use super::unified_dtos::UnifiedBucketByTime;
+use crate::cli::Provider;
+use crate::prelude::*;
+
+/// All providers are asked for 1-hour buckets, aligned to the hour.
+const BUCKET_WIDTH_IN_SEC: i64 = 3600;
+
+/// How long to wait after a bucket ends before trusting it as closed.
+/// Usage can show up in the report a while after it happened.
+const SETTLE_TIME_IN_SEC: i64 = 3600;
+
+/// Bump it whenever the stored buckets change shape or meaning, so old stores are fetched again
+/// instead of being read wrong. Closed buckets would otherwise be kept for good.
+const FORMAT_VERSION: u32 = 1;
+
+/// A time range to fetch, in unix seconds. The end is exclusive, `None` means now.
+pub type FetchRange = (i64, Option<i64>);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BucketStore {
+    /// See `FORMAT_VERSION`.
+    format_version: u32,
+
+    /// Buckets that are over, keyed by their start.
+    closed: BTreeMap<i64, UnifiedBucketByTime>,
+
+    /// The stretch of time `closed` holds entirely, `[from, until)`.
+    coverage: Option<(i64, i64)>,
+
+    /// Buckets after the coverage, still open when they were fetched at `fetched_at`.
+    /// They are only good for the TTL, then they have to be fetched again.
+    open: Vec<UnifiedBucketByTime>,
+
+    /// Where the fetch of the open buckets ended, `None` meaning `fetched_at`.
+    open_until: Option<i64>,
+
+    /// When the open buckets were fetched.
+    fetched_at: i64,
+}
+
+impl Default for BucketStore {
+    fn default() -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            closed: BTreeMap::new(),
+            coverage: None,
+            open: vec![],
+            open_until: None,
+            fetched_at: 0,
+        }
+    }
+}
+
+impl BucketStore {
+    /// Works out what has to be fetched from the API to answer `[start, end)`.
+    ///
+    /// - Time before the coverage is fetched up to where the coverage starts.
+    /// - Time after the coverage is fetched from where it ends, unless the open buckets are
+    ///   still fresh. That includes any gap up to `start`, so the coverage is always one piece,
+    ///   and the closed buckets are never thrown away.
+    /// - An empty coverage, from a fetch that was all open buckets, doesn't count.
+    pub fn plan(&self, start: i64, end: Option<i64>, now: i64, ttl: &i64) -> Vec<FetchRange> {
+        let start = floor_to_bucket(start);
+
+        let Some((covered_from, covered_until)) =
+            self.coverage.filter(|(from, until)| from < until)
+        else {
+            return vec![(start, end)];
+        };
+
+        let mut fetches = vec![];
+
+        if start < covered_from {
+            fetches.push((start, Some(covered_from)));
+        }
+
+        let is_open_fresh = self.fetched_at + ttl * 60 > now && self.open_until == end;
+
+        if end.unwrap_or(now) > covered_until && !is_open_fresh {
+            fetches.push((covered_until, end));
+        }
+
+        fetches
+    }
+
+    /// Puts freshly fetched buckets of `range` into the store.
+    ///
+    /// Buckets that ended before the settle time are closed and kept for good,
+    /// the rest replace the open ones, unless the fetch was a backfill before the coverage.
+    /// Only closed buckets widen the coverage, the open ones have to be fetched again anyway.
+    pub fn merge(&mut self, range: FetchRange, buckets: Vec<UnifiedBucketByTime>, now: i64) {
+        let (fetched_from, fetched_until) = range;
+
+        let settled_until = floor_to_bucket(now - SETTLE_TIME_IN_SEC);
+        let closed_until = floor_to_bucket(fetched_until.unwrap_or(now).min(settled_until));
+        let closed_until = closed_until.max(fetched_from);
+
+        let (closed, open): (Vec<_>, Vec<_>) = buckets
+            .into_iter()
+            .partition(|bucket| bucket.end <= closed_until);
+
+        self.closed
+            .extend(closed.into_iter().map(|bucket| (bucket.start, bucket)));
+
+        // `plan` only asks for ranges next to the coverage, but an empty coverage, or anything
+        // else that would leave a hole, starts over from this fetch.
+        let is_backfill = self.coverage.is_some_and(|(from, until)| {
+            from < until && fetched_until.is_some_and(|fetched_until| fetched_until <= from)
+        });
+
+        self.coverage = match self.coverage {
+            Some((from, until))
+                if from < until && fetched_from <= until && closed_until >= from =>
+            {
+                Some((from.min(fetched_from), until.max(closed_until)))
+            }
+            _ => Some((fetched_from, closed_until)),
+        };
+
+        // Only a fetch that went past the coverage knows about the open buckets.
+        // A backfill's unsettled end is right before the coverage, not after it.
+        if !is_backfill && fetched_until.is_none_or(|until| until > closed_until) {
+            self.open = open;
+            self.open_until = fetched_until;
+            self.fetched_at = now;
+        }
+    }
+
+    /// Every bucket that starts within `[start, end)`, in order.
+    pub fn select(&self, start: i64, end: Option<i64>) -> Vec<UnifiedBucketByTime> {
+        let start = floor_to_bucket(start);
+        let is_in_range = |bucket: &&UnifiedBucketByTime| {
+            bucket.start >= start && end.is_none_or(|end| bucket.start < end)
+        };
+
+        let closed = self.closed.values().filter(is_in_range);
+        let open = self
+            .open
+            .iter()
+            .filter(|bucket| !self.closed.contains_key(&bucket.start))
+            .filter(is_in_range);
+
+        closed.chain(open).cloned().collect()
+    }
+}
+
+/// Compose the platform-specific bucket store file path for a provider.
+///
+/// The API key is a part of the hash, so two organizations on the same machine never share
+/// their data. It never shows up in the path as it is.
+/// So is the base URL, a mock server's buckets never end up in the real ones.
+///
+/// The resulting path follows the pattern:
+/// `{cache_dir}/meter/buckets/anthropic_7a2f4c91b0e3`
+pub fn create_bucket_store_file_path(
+    provider: &Provider,
+    key: &str,
+    base_url: &str,
+) -> AppResult<PathBuf> {
+    let dir = store_dir()?;
+
+    let file_name = format!(
+        "{}_{}",
+        provider,
+        generate_cache_filename(&format!("{key}@{base_url}"))
+    );
+
+    Ok(dir.join(file_name))
+}
+
+/// `{cache_dir}/meter/buckets`, where the stores live.
+pub fn store_dir() -> AppResult<PathBuf> {
+    Ok(meter_cache_dir()?.join("buckets"))
+}
+
+/// A store on disk, as `meter cache` sees it.
+#[derive(Debug)]
+pub struct StoreEntry {
+    /// The file name, e.g. `anthropic_7a2f4c91b0e3`.
+    pub name: String,
+    pub path: PathBuf,
+    pub size_in_bytes: u64,
+
+    /// When it was last written, the file's mtime.
+    pub modified_at: Timestamp,
+}
+
+/// Every store in the store directory, most recently written first.
+pub fn try_list_stores() -> AppResult<Vec<StoreEntry>> {
+    let dir = store_dir()?;
+
+    if !dir.try_exists().into_diagnostic()? {
+        return Ok(vec![]);
+    }
+
+    let mut stores = vec![];
+
+    for dir_entry in fs::read_dir(&dir).into_diagnostic()? {
+        let path = dir_entry.into_diagnostic()?.path();
+
+        // Same as the cached results, locks and temporary files have an extension.
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if path.extension().is_some() {
+            continue;
+        }
+
+        let metadata = fs::metadata(&path).into_diagnostic()?;
+        let modified_at =
+            Timestamp::try_from(metadata.modified().into_diagnostic()?).into_diagnostic()?;
+
+        stores.push(StoreEntry {
+            name: name.to_owned(),
+            size_in_bytes: metadata.len(),
+            modified_at,
+            path,
+        });
+    }
+
+    stores.sort_by_key(|store| std::cmp::Reverse(store.modified_at));
+
+    Ok(stores)
+}
+
+/// Removes a store and its lock. Returns `false`, and leaves it alone, if a fetch holds it.
+pub fn try_remove_store(store: &StoreEntry) -> AppResult<bool> {
+    let Some(lock) = try_acquire_lock(&store.path, Duration::ZERO)? else {
+        return Ok(false);
+    };
+
+    fs::remove_file(&store.path).into_diagnostic()?;
+
+    // Unlinked before it's released, see `try_remove_entry`.
+    fs::remove_file(store.path.with_extension("lock")).into_diagnostic()?;
+    drop(lock);
+
+    Ok(true)
+}
+
+/// Loads the store of a provider, or an empty one if there is none yet.
+///
+/// A file that doesn't parse, or isn't even UTF-8, is treated like a missing one, the API can
+/// always tell us again. So is one of another format version.
+pub fn try_load(store_file_path: &std::path::Path) -> AppResult<BucketStore> {
+    if !store_file_path.try_exists().into_diagnostic()? {
+        return Ok(BucketStore::default());
+    }
+
+    let content = fs::read(store_file_path).into_diagnostic()?;
+
+    let store = serde_json::from_slice::<BucketStore>(&content)
+        .ok()
+        .filter(|store| store.format_version == FORMAT_VERSION)
+        .unwrap_or_default();
+
+    Ok(store)
+}
+
+/// Writes the store back.
+pub fn try_save(store_file_path: &std::path::Path, store: &BucketStore) -> AppResult<()> {
+    let body_string = serde_json::to_string(store)
+        .into_diagnostic()
+        .wrap_err("Failed to serialize usage buckets for the cache.")?;
+
+    try_write_atomically(store_file_path, &body_string)
+}
+
+/// Moves a unix timestamp back to the start of its hourly bucket.
+fn floor_to_bucket(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(BUCKET_WIDTH_IN_SEC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = BUCKET_WIDTH_IN_SEC;
+    const MINUTE: i64 = 60;
+    const TTL: i64 = 5;
+
+    /// Some midnight, UTC.
+    const MIDNIGHT: i64 = 20_000 * 24 * HOUR;
+
+    fn bucket(start: i64) -> UnifiedBucketByTime {
+        UnifiedBucketByTime {
+            start,
+            end: start + HOUR,
+            results: vec![],
+            provider: Provider::Anthropic,
+        }
+    }
+
+    /// The starts of the hourly buckets within `[from, until)`.
+    fn hours(from: i64, until: i64) -> Vec<i64> {
+        (from..until).step_by(HOUR as usize).collect()
+    }
+
+    /// What the API answers at `now`, a bucket for every hour that has begun.
+    fn api((from, until): FetchRange, now: i64) -> Vec<UnifiedBucketByTime> {
+        let until = until.unwrap_or(now).min(now);
+
+        hours(from, until).into_iter().map(bucket).collect()
+    }
+
+    /// Runs a report the way main does. Returns the starts of its buckets, and what was fetched.
+    fn report(
+        store: &mut BucketStore,
+        start: i64,
+        end: Option<i64>,
+        now: i64,
+    ) -> (Vec<i64>, Vec<FetchRange>) {
+        let fetches = store.plan(start, end, now, &TTL);
+
+        for range in &fetches {
+            store.merge(*range, api(*range, now), now);
+        }
+
+        let starts = store.select(start, end).iter().map(|b| b.start).collect();
+
+        (starts, fetches)
+    }
+
+    #[test]
+    fn a_fresh_store_fetches_everything() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 10 * HOUR + 30 * MINUTE;
+
+        let (starts, fetches) = report(&mut store, MIDNIGHT, None, now);
+
+        assert_eq!(fetches, vec![(MIDNIGHT, None)]);
+        assert_eq!(starts, hours(MIDNIGHT, now));
+    }
+
+    #[test]
+    fn open_buckets_are_fetched_again_after_the_ttl() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 10 * HOUR + 30 * MINUTE;
+        report(&mut store, MIDNIGHT, None, now);
+
+        let (_, fetches) = report(&mut store, MIDNIGHT, None, now + MINUTE);
+        assert_eq!(fetches, vec![]);
+
+        let later = now + TTL * MINUTE;
+        let (starts, fetches) = report(&mut store, MIDNIGHT, None, later);
+        assert_eq!(fetches, vec![(MIDNIGHT + 9 * HOUR, None)]);
+        assert_eq!(starts, hours(MIDNIGHT, later));
+    }
+
+    #[test]
+    fn a_bucket_closes_a_settle_time_after_it_ended() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 2 * HOUR;
+
+        report(&mut store, MIDNIGHT, None, now);
+
+        // The first bucket ended exactly an hour ago, the second one just now.
+        assert_eq!(store.coverage, Some((MIDNIGHT, MIDNIGHT + HOUR)));
+        assert_eq!(
+            store.closed.keys().copied().collect::<Vec<_>>(),
+            vec![MIDNIGHT]
+        );
+        assert_eq!(
+            store.open.iter().map(|b| b.start).collect::<Vec<_>>(),
+            hours(MIDNIGHT + HOUR, now)
+        );
+
+        // A second later, nothing more has settled.
+        let (_, fetches) = report(&mut store, MIDNIGHT, None, now + TTL * MINUTE);
+        assert_eq!(fetches, vec![(MIDNIGHT + HOUR, None)]);
+        assert_eq!(store.coverage, Some((MIDNIGHT, MIDNIGHT + HOUR)));
+    }
+
+    #[test]
+    fn a_backfill_into_the_settle_time_keeps_every_bucket() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 42 * MINUTE;
+
+        // --since 0h, nothing has settled yet.
+        report(&mut store, MIDNIGHT, None, now);
+
+        // --since 2h, the 23:00 bucket is still open.
+        let (starts, _) = report(&mut store, MIDNIGHT - 2 * HOUR, None, now);
+        assert_eq!(starts, hours(MIDNIGHT - 2 * HOUR, now));
+
+        // --since 3h.
+        let (starts, _) = report(&mut store, MIDNIGHT - 3 * HOUR, None, now);
+        assert_eq!(starts, hours(MIDNIGHT - 3 * HOUR, now));
+
+        // Once the TTL is over, and again once everything settled.
+        for later in [now + TTL * MINUTE, MIDNIGHT + 5 * HOUR] {
+            let (starts, _) = report(&mut store, MIDNIGHT - 3 * HOUR, None, later);
+            assert_eq!(starts, hours(MIDNIGHT - 3 * HOUR, later));
+        }
+    }
+
+    #[test]
+    fn a_backfill_fetches_only_what_is_before_the_coverage() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 10 * HOUR + 30 * MINUTE;
+        report(&mut store, MIDNIGHT, None, now);
+
+        let (starts, fetches) = report(&mut store, MIDNIGHT - 24 * HOUR, None, now + MINUTE);
+
+        assert_eq!(fetches, vec![(MIDNIGHT - 24 * HOUR, Some(MIDNIGHT))]);
+        assert_eq!(starts, hours(MIDNIGHT - 24 * HOUR, now));
+        assert_eq!(
+            store.coverage,
+            Some((MIDNIGHT - 24 * HOUR, MIDNIGHT + 9 * HOUR))
+        );
+    }
+
+    #[test]
+    fn a_range_after_the_coverage_fetches_the_gap_and_keeps_the_closed_buckets() {
+        let mut store = BucketStore::default();
+        let month_ago = MIDNIGHT - 30 * 24 * HOUR;
+        let now = MIDNIGHT + 10 * HOUR + 30 * MINUTE;
+        report(&mut store, month_ago, None, now);
+
+        // The next day, --since 0h, a few minutes after midnight.
+        let tomorrow = MIDNIGHT + 24 * HOUR;
+        let later = tomorrow + 20 * MINUTE;
+        let (starts, fetches) = report(&mut store, tomorrow, None, later);
+
+        assert_eq!(fetches, vec![(MIDNIGHT + 9 * HOUR, None)]);
+        assert_eq!(starts, hours(tomorrow, later));
+
+        // --since 30d then has nothing left to fetch.
+        let (starts, fetches) = report(&mut store, month_ago, None, later + MINUTE);
+
+        assert_eq!(fetches, vec![]);
+        assert_eq!(starts, hours(month_ago, later));
+    }
+
+    #[test]
+    fn a_range_with_an_end_is_answered_from_the_closed_buckets() {
+        let mut store = BucketStore::default();
+        let now = MIDNIGHT + 10 * HOUR + 30 * MINUTE;
+        report(&mut store, MIDNIGHT - 24 * HOUR, None, now);
+
+        let (starts, fetches) = report(
+            &mut store,
+            MIDNIGHT - 12 * HOUR,
+            Some(MIDNIGHT),
+            now + TTL * MINUTE,
+        );
+
+        assert_eq!(fetches, vec![]);
+        assert_eq!(starts, hours(MIDNIGHT - 12 * HOUR, MIDNIGHT));
+    }
+
+    #[test]
+    fn a_garbled_store_loads_empty() {
+        let dir =
+            std::env::temp_dir().join(format!("meter-test-{}-garbled-store", std::process::id()));
+        let path = dir.join("anthropic_7a2f4c91b0e3");
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, b"\xff\xfe{\"half").unwrap();
+
+        let store = try_load(&path).unwrap();
+
+        assert!(store.select(MIDNIGHT, None).is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
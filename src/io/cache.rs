@@ -1,66 +1,489 @@
 use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use jiff::{Timestamp, ToSpan};
+use twox_hash::XxHash64;
 
 use crate::prelude::*;
 
+/// Every cached result starts with this, followed by the hash of its arguments.
+const ENTRY_PREFIX: &str = "cache_";
+
+/// Bump it whenever `CacheEnvelope` changes shape.
+/// Entries of any other version are treated as missing, and written over.
+const FORMAT_VERSION: u32 = 1;
+
+/// What a cache file holds: the result, plus enough to tell where and when it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEnvelope {
+    pub format_version: u32,
+
+    /// The meter that wrote it, for `meter cache show`.
+    pub meter_version: String,
+
+    /// The TTL counts from here, not from the file's mtime.
+    pub created_at: Timestamp,
+
+    pub meta: CacheMeta,
+
+    /// The rendered output, ready to print.
+    pub payload: String,
+}
+
+/// Where an entry came from. The hash alone can't tell which command created it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMeta {
+    /// The command line that created the entry, without the keys.
+    pub command: String,
+
+    /// Where the report started and ended, as resolved at the time.
+    pub report_start: Timestamp,
+    pub report_end: Option<Timestamp>,
+
+    /// The TTL it was written with, in minutes. Shorter than `--ttl-minutes` for partial ones.
+    pub ttl_minutes: i64,
+
+    /// Whether some providers were missing from it, with `--partial`.
+    /// A hit on it is still partial, and exits like one.
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// What a hit gives back.
+#[derive(Debug)]
+pub struct CachedOutput {
+    pub payload: String,
+    pub partial: bool,
+}
+
+impl From<CacheEnvelope> for CachedOutput {
+    fn from(envelope: CacheEnvelope) -> Self {
+        CachedOutput {
+            payload: envelope.payload,
+            partial: envelope.meta.partial,
+        }
+    }
+}
+
+/// A cached result on disk, as `meter cache` sees it.
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub hash: String,
+    pub path: PathBuf,
+    pub size_in_bytes: u64,
+
+    /// From the envelope, or the file's mtime if there is none.
+    pub created_at: Timestamp,
+
+    /// `None` for entries that are corrupt, unreadable, or in an older format.
+    pub envelope: Option<CacheEnvelope>,
+}
+
+impl CacheEntry {
+    /// Whether the entry would still be answered from, by the TTL it was written with.
+    /// Entries without an envelope never are.
+    pub fn is_expired(&self, system_now: &Timestamp) -> bool {
+        self.envelope
+            .as_ref()
+            .is_none_or(|envelope| envelope.is_expired(system_now, &envelope.meta.ttl_minutes))
+    }
+}
+
+impl CacheEnvelope {
+    fn is_expired(&self, system_now: &Timestamp, ttl: &i64) -> bool {
+        self.created_at + ttl.minutes() <= *system_now
+    }
+}
+
+/// `{cache_dir}/meter`, everything meter caches is under it.
+pub fn meter_cache_dir() -> AppResult<PathBuf> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| miette!("Could not find a cache directory."))?
+        .join("meter");
+
+    Ok(dir)
+}
+
+/// `{cache_dir}/meter/claude`, where the cached results live.
+pub fn cache_dir() -> AppResult<PathBuf> {
+    Ok(meter_cache_dir()?.join("claude"))
+}
+
+/// Compose the platform-specific cache file path for a given argument signature.
+///
+/// The resulting path follows the pattern:
+/// `{cache_dir}/meter/claude/cache_7a2f4c91b0e3`
+pub fn create_cache_file_path(args_signature: &str) -> AppResult<PathBuf> {
+    let file_name = format!("{ENTRY_PREFIX}{args_signature}");
+
+    Ok(cache_dir()?.join(file_name))
+}
+
 /// Retrieves cached content if it exists and hasn't expired.
-/// Returns `None` if the file doesn't exist or has exceeded its TTL.
+/// Returns `None` if the file doesn't exist, has exceeded its TTL, or can't be read as an entry.
+///
+/// The entry's own TTL counts too, when it's shorter, a partial one's for example.
 pub fn try_retrieve_cache(
-    cache_file_path: &std::path::Path,
+    cache_file_path: &Path,
     ttl: &i64,
     system_now: &Timestamp,
-) -> AppResult<Option<String>> {
-    if !cache_file_path.try_exists().into_diagnostic()? {
-        return Ok(None);
+) -> AppResult<Option<CachedOutput>> {
+    let content = try_read_envelope(cache_file_path)?
+        .filter(|envelope| {
+            let ttl = ttl.min(&envelope.meta.ttl_minutes);
+
+            !envelope.is_expired(system_now, ttl)
+        })
+        .map(CachedOutput::from);
+
+    Ok(content)
+}
+
+/// Retrieves cached content regardless of its age.
+/// Returns `None` if the file doesn't exist, or can't be read as an entry.
+pub fn try_retrieve_stale_cache(cache_file_path: &Path) -> AppResult<Option<CachedOutput>> {
+    let content = try_read_envelope(cache_file_path)?.map(CachedOutput::from);
+
+    Ok(content)
+}
+
+/// An advisory lock on a cache file, shared by every meter process on the machine.
+/// It's released when this is dropped, or when the process dies.
+pub struct CacheLock {
+    _file: fs::File,
+}
+
+/// Waits for the exclusive lock of a cache file, `cache_7a2f4c91b0e3.lock` next to it.
+/// Returns `None` if another process still holds it after the timeout.
+///
+/// `cache clear` unlinks lock files while holding them. Whoever was waiting on one gets a lock
+/// on a file nobody else can find anymore, so it checks and starts over on the new one.
+pub fn try_acquire_lock(cache_file_path: &Path, timeout: Duration) -> AppResult<Option<CacheLock>> {
+    // Polling is good enough here, nobody is in a hurry while another process is fetching.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    if let Some(parent) = cache_file_path.parent() {
+        fs::create_dir_all(parent).into_diagnostic()?;
     }
 
-    if is_cache_expired(cache_file_path, system_now, ttl)? {
-        return Ok(None);
+    let lock_file_path = cache_file_path.with_extension("lock");
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let lock_file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&lock_file_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("Could not open the lock file {}", lock_file_path.display())
+            })?;
+
+        loop {
+            match lock_file.try_lock() {
+                Ok(()) => break,
+                Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    std::thread::sleep(POLL_INTERVAL)
+                }
+                Err(fs::TryLockError::WouldBlock) => return Ok(None),
+                Err(fs::TryLockError::Error(e)) => return Err(e).into_diagnostic(),
+            }
+        }
+
+        if is_still_linked(&lock_file, &lock_file_path).into_diagnostic()? {
+            return Ok(Some(CacheLock { _file: lock_file }));
+        }
     }
+}
 
-    let content = Some(fs::read_to_string(cache_file_path).into_diagnostic()?);
+/// Whether `path` still names the file we have open, that nobody unlinked or replaced it.
+#[cfg(unix)]
+fn is_still_linked(file: &fs::File, path: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
 
-    Ok(content)
+    let held = file.metadata()?;
+
+    match fs::metadata(path) {
+        Ok(current) => Ok(held.dev() == current.dev() && held.ino() == current.ino()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Elsewhere a file that is open can't be replaced, only marked for deletion, and opening it
+/// fails until then. So the one we have is the one at `path`.
+#[cfg(not(unix))]
+fn is_still_linked(_file: &fs::File, _path: &Path) -> std::io::Result<bool> {
+    Ok(true)
 }
 
 /// Writes content to the cache file, but only if the cache is expired or doesn't exist.
-/// Skips writing if the cache is still alive to preserve its modification time.
+/// Skips writing if the cache is still alive to preserve its creation time.
 pub fn try_write_cache(
-    cache_file_path: &std::path::Path,
+    cache_file_path: &Path,
     body_string: &str,
+    meta: &CacheMeta,
     ttl: &i64,
     system_now: &Timestamp,
 ) -> AppResult<()> {
+    let is_cache_alive = try_read_envelope(cache_file_path)?
+        .is_some_and(|envelope| !envelope.is_expired(system_now, ttl));
+
+    // Do not touch it if the cache is still alive.
+    // If we rewrite it, the countdown will change.
+    // Without this check, every cache hit would reset the ttl.
+    if is_cache_alive {
+        return Ok(());
+    }
+
+    let envelope = CacheEnvelope {
+        format_version: FORMAT_VERSION,
+        meter_version: env!("CARGO_PKG_VERSION").to_owned(),
+        created_at: *system_now,
+        meta: meta.clone(),
+        payload: body_string.to_owned(),
+    };
+
+    let envelope_string = serde_json::to_string(&envelope)
+        .into_diagnostic()
+        .wrap_err("Failed to serialize the cache entry.")?;
+
+    try_write_atomically(cache_file_path, &envelope_string)
+}
+
+/// Writes the whole file or nothing at all.
+///
+/// The content goes to a temporary file next to the target first, then takes its place with a
+/// rename, so a reader never sees half of it. A status bar would otherwise print half a number.
+pub fn try_write_atomically(file_path: &Path, contents: &str) -> AppResult<()> {
     // Ensure the directory exists.
-    if let Some(parent) = cache_file_path.parent() {
+    if let Some(parent) = file_path.parent() {
         fs::create_dir_all(parent).into_diagnostic()?;
     }
 
-    let is_cache_alive = cache_file_path.try_exists().into_diagnostic()?
-        && !is_cache_expired(cache_file_path, system_now, ttl)?;
+    // Per process, so two writers never share a temporary file.
+    let mut temp_file_name = file_path.file_name().unwrap_or_default().to_owned();
+    temp_file_name.push(format!(".{}.tmp", std::process::id()));
 
-    // Do not touch it if the cache is still alive.
-    // Because we rely on the mtime, if we touch it, the countdown will change.
-    // Without this check, every cache hit would reset the ttl.
-    if is_cache_alive {
+    let temp_file_path = file_path.with_file_name(temp_file_name);
+
+    fs::write(&temp_file_path, contents).into_diagnostic()?;
+
+    if let Err(e) = fs::rename(&temp_file_path, file_path) {
+        let _ = fs::remove_file(&temp_file_path);
+
+        return Err(e).into_diagnostic();
+    }
+
+    Ok(())
+}
+
+/// Every cached result in the cache directory, newest first.
+pub fn try_list_entries() -> AppResult<Vec<CacheEntry>> {
+    let dir = cache_dir()?;
+
+    if !dir.try_exists().into_diagnostic()? {
+        return Ok(vec![]);
+    }
+
+    let mut entries = vec![];
+
+    for dir_entry in fs::read_dir(&dir).into_diagnostic()? {
+        let path = dir_entry.into_diagnostic()?.path();
+
+        // Locks and temporary files have an extension, the entries themselves don't.
+        let hash = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if path.extension().is_none() => name.strip_prefix(ENTRY_PREFIX),
+            _ => None,
+        };
+
+        if let Some(hash) = hash {
+            entries.push(try_read_entry(hash.to_owned(), path)?);
+        }
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+
+    Ok(entries)
+}
+
+/// Finds an entry by its hash, with or without the `cache_` prefix.
+pub fn try_find_entry(hash: &str) -> AppResult<Option<CacheEntry>> {
+    let hash = hash.strip_prefix(ENTRY_PREFIX).unwrap_or(hash);
+    let path = create_cache_file_path(hash)?;
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some(try_read_entry(hash.to_owned(), path)?))
+}
+
+/// Removes an entry, and its lock unless someone is holding it right now.
+///
+/// The lock file is unlinked while we still hold it. Released first, another meter could lock
+/// it in between, while a third one creates a new one, and both would refresh at once.
+pub fn try_remove_entry(entry: &CacheEntry) -> AppResult<()> {
+    fs::remove_file(&entry.path).into_diagnostic()?;
+
+    if let Some(_lock) = try_acquire_lock(&entry.path, Duration::ZERO)? {
+        fs::remove_file(entry.path.with_extension("lock")).into_diagnostic()?;
+    }
+
+    Ok(())
+}
+
+/// Removes the locks in `dir` left behind by refreshes that never wrote anything, a failed
+/// fetch for example. Skips the ones someone is holding right now.
+pub fn try_remove_orphan_locks(dir: &Path) -> AppResult<()> {
+    if !dir.try_exists().into_diagnostic()? {
         return Ok(());
     }
 
-    fs::write(cache_file_path, body_string).into_diagnostic()?;
+    for dir_entry in fs::read_dir(dir).into_diagnostic()? {
+        let lock_path = dir_entry.into_diagnostic()?.path();
+        let cache_file_path = lock_path.with_extension("");
+
+        let is_orphan = lock_path
+            .extension()
+            .is_some_and(|extension| extension == "lock")
+            && !cache_file_path.try_exists().into_diagnostic()?;
+
+        // Unlinked while held, like in `try_remove_entry`.
+        if is_orphan && let Some(_lock) = try_acquire_lock(&cache_file_path, Duration::ZERO)? {
+            fs::remove_file(lock_path).into_diagnostic()?;
+        }
+    }
 
     Ok(())
 }
 
-/// Determines if a cache file has exceeded its time-to-live based on modification time.
-fn is_cache_expired(
-    cache_file_path: &std::path::Path,
-    system_now: &Timestamp,
-    ttl: &i64,
-) -> AppResult<bool> {
-    let metadata = fs::metadata(cache_file_path).into_diagnostic()?;
-    let file_mtime = metadata.modified().into_diagnostic()?;
-    let expiration_time = Timestamp::try_from(file_mtime).into_diagnostic()? + ttl.minutes();
+fn try_read_entry(hash: String, path: PathBuf) -> AppResult<CacheEntry> {
+    let metadata = fs::metadata(&path).into_diagnostic()?;
+
+    // One unreadable entry must not take `cache list` and `cache clear` down with it,
+    // clearing it is the way out. It's shown without an envelope, and removed like any other.
+    let envelope = try_read_envelope(&path).unwrap_or(None);
+
+    let created_at = match &envelope {
+        Some(envelope) => envelope.created_at,
+        None => Timestamp::try_from(metadata.modified().into_diagnostic()?).into_diagnostic()?,
+    };
 
-    Ok(expiration_time <= *system_now)
+    Ok(CacheEntry {
+        hash,
+        size_in_bytes: metadata.len(),
+        created_at,
+        envelope,
+        path,
+    })
+}
+
+/// Reads a cache file as an envelope.
+///
+/// A file that doesn't parse, isn't even UTF-8, or was written in another format, is treated
+/// like a missing one. It's only a cache, the API can always tell us again.
+/// Only a file that can't be read at all is an error.
+fn try_read_envelope(cache_file_path: &Path) -> AppResult<Option<CacheEnvelope>> {
+    if !cache_file_path.try_exists().into_diagnostic()? {
+        return Ok(None);
+    }
+
+    // Bytes, not a string, so a torn or garbled file fails to decode instead of to read.
+    let content = fs::read(cache_file_path).into_diagnostic()?;
+
+    let envelope = serde_json::from_slice::<CacheEnvelope>(&content)
+        .ok()
+        .filter(|envelope| envelope.format_version == FORMAT_VERSION);
+
+    Ok(envelope)
+}
+
+/// Hashes serialized arguments into a cache filename.
+///
+/// Uses XxHash64 to produce a fast, deterministic hash of the input,
+/// then formats it as hexadecimal.
+///
+/// Returns a short string like "7a2f4c91b0e3".
+pub fn generate_cache_filename(serialized_args: &str) -> String {
+    let mut hasher = XxHash64::default();
+
+    hasher.write(serialized_args.as_bytes());
+    let hashed = hasher.finish();
+
+    // Returns something like "7a2f4c91b0e3"
+    format!("{:x}", hashed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system's temp dir, for one test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("meter-test-{}-{name}", std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn a_garbled_entry_is_a_miss() {
+        let dir = temp_dir("garbled-entry");
+        let path = dir.join(format!("{ENTRY_PREFIX}7a2f4c91b0e3"));
+
+        fs::write(&path, b"\xff\xfe{\"half").unwrap();
+
+        let now = Timestamp::now();
+
+        assert!(try_retrieve_cache(&path, &60, &now).unwrap().is_none());
+        assert!(try_retrieve_stale_cache(&path).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn an_unreadable_entry_is_listed_without_an_envelope() {
+        let dir = temp_dir("unreadable-entry");
+        let path = dir.join(format!("{ENTRY_PREFIX}7a2f4c91b0e3"));
+
+        // Can't be read as a file, even by root.
+        fs::create_dir(&path).unwrap();
+
+        let entry = try_read_entry("7a2f4c91b0e3".to_owned(), path).unwrap();
+
+        assert!(entry.envelope.is_none());
+        assert!(entry.is_expired(&Timestamp::now()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_lock_unlinked_while_waited_on_is_not_kept() {
+        let dir = temp_dir("unlinked-lock");
+        let path = dir.join(format!("{ENTRY_PREFIX}7a2f4c91b0e3"));
+
+        let held = try_acquire_lock(&path, Duration::ZERO).unwrap().unwrap();
+
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| try_acquire_lock(&path, Duration::from_secs(5)).unwrap());
+
+            // Let it start waiting on the old file, then unlink and release it like `clear` does.
+            std::thread::sleep(Duration::from_millis(300));
+            fs::remove_file(path.with_extension("lock")).unwrap();
+            drop(held);
+
+            let _waited = waiter.join().unwrap().expect("the waiter gets the lock");
+
+            // A newcomer finds the lock the waiter holds, not a fresh one of its own.
+            assert!(try_acquire_lock(&path, Duration::ZERO).unwrap().is_none());
+        });
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
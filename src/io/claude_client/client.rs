@@ -2,24 +2,27 @@ use jiff::Zoned;
 
 use super::dtos::{BucketByTime, ResponsePage};
 use crate::app::App;
-use crate::error::Error::AnthropicRateLimitExceeded;
+use crate::io::response::{REQUEST_TIMEOUT, try_read_response};
+use crate::io::retry::send_with_retry;
 use crate::prelude::*;
 
 const API_VERSION: &str = "2023-06-01";
+/// How the spinner and the errors call it.
+pub const PROVIDER_NAME: &str = "Anthropic";
+/// Where a 429 sends the user.
+const RATE_LIMIT_DOCS: &str = "https://platform.claude.com/docs/en/api/rate-limits";
 const BUCKET_WIDTH: &str = "1h";
-const USAGE_REPORT_ENDPOINT: &str =
-    "https://api.anthropic.com/v1/organizations/usage_report/messages";
+// Appended to `--anthropic-base-url`, which points to the real API by default.
+const USAGE_REPORT_PATH: &str = "/v1/organizations/usage_report/messages";
 const GAP_TIME_BETWEEN_FETCH_IN_SEC: u64 = 5;
-// For dev test.
-// const USAGE_REPORT_ENDPOINT: &str = "https://httpbin.org/status/429";
 
 pub fn fetch(
     ctx: &App,
+    key: &str,
+    base_url: &str,
     starting_at: &Zoned,
     ending_at: Option<&Zoned>,
 ) -> AppResult<Vec<BucketByTime>> {
-    let key = ctx.cli.try_get_anthropic_key()?.to_owned();
-
     // RFC 3339, this API expects this format.
     let starting_at_timestamp = starting_at.timestamp().to_string();
     let ending_at_timestamp = ending_at.map(|time| time.timestamp().to_string());
@@ -37,14 +40,17 @@ pub fn fetch(
 
     while has_more {
         // First things first, give users something to look at.
-        ctx.display.update_spin_message(progress_text(page_number));
+        ctx.display
+            .update_progress(PROVIDER_NAME, progress_text(page_number));
 
         if page_number > 1 {
             wait();
         }
 
         let body = inner_fetch(
-            &key,
+            ctx,
+            key,
+            base_url,
             &starting_at_timestamp,
             ending_at_timestamp.as_deref(),
             next_page.as_deref(),
@@ -64,47 +70,62 @@ pub fn fetch(
 }
 
 fn inner_fetch(
+    ctx: &App,
     key: &str,
+    base_url: &str,
     starting_at_timestamp: &str,
     ending_at_timestamp: Option<&str>,
     next_page: Option<&str>,
 ) -> AppResult<ResponsePage> {
-    let request = ureq::get(USAGE_REPORT_ENDPOINT)
-        .header("anthropic-version", API_VERSION)
-        .header("X-Api-Key", key)
-        // ranging, sizing.
-        .query("starting_at", starting_at_timestamp)
-        .query("bucket_width", BUCKET_WIDTH)
-        // grouping.
-        .query("group_by[]", "model")
-        .query("group_by[]", "context_window")
-        .query("group_by[]", "workspace_id")
-        .query("group_by[]", "api_key_id");
-
-    // optional page.
-    let request = match next_page {
-        Some(page_token) => request.query("page", page_token),
-        None => request,
-    };
-
-    // optional ending_at.
-    let request = match ending_at_timestamp {
-        Some(timestamp) => request.query("ending_at", timestamp),
-        None => request,
-    };
-
-    let mut response = match request.call() {
-        Ok(res) => res,
-        Err(ureq::Error::StatusCode(429)) => bail!(AnthropicRateLimitExceeded),
-        Err(e) => bail!(e),
+    let endpoint = usage_report_endpoint(base_url);
+
+    // Built from scratch on every attempt, a request can only be sent once.
+    let send = || {
+        let request = ureq::get(&endpoint)
+            .header("anthropic-version", API_VERSION)
+            .header("X-Api-Key", key)
+            // ranging, sizing.
+            .query("starting_at", starting_at_timestamp)
+            .query("bucket_width", BUCKET_WIDTH)
+            // grouping.
+            .query("group_by[]", "model")
+            .query("group_by[]", "context_window")
+            .query("group_by[]", "workspace_id")
+            .query("group_by[]", "api_key_id")
+            .query("group_by[]", "service_tier");
+
+        // optional page.
+        let request = match next_page {
+            Some(page_token) => request.query("page", page_token),
+            None => request,
+        };
+
+        // optional ending_at.
+        let request = match ending_at_timestamp {
+            Some(timestamp) => request.query("ending_at", timestamp),
+            None => request,
+        };
+
+        // Statuses are handled below, and a 429 has to keep its retry-after.
+        request
+            .config()
+            .http_status_as_error(false)
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .call()
     };
 
-    let body = response
-        .body_mut()
-        .read_json::<ResponsePage>()
-        .into_diagnostic()?;
+    try_read_response(
+        PROVIDER_NAME,
+        &endpoint,
+        send_with_retry(ctx, PROVIDER_NAME, send),
+        RATE_LIMIT_DOCS,
+    )
+}
 
-    Ok(body)
+/// Tolerates a trailing slash on the base URL, people copy them from browsers.
+fn usage_report_endpoint(base_url: &str) -> String {
+    format!("{}{}", base_url.trim_end_matches('/'), USAGE_REPORT_PATH)
 }
 
 /// Keep ourselves safe. We can wait.
@@ -119,7 +140,7 @@ fn wait() {
 /// User should see a proper visual feedback becasue fetching will take quite some time
 /// since I've put a big gap between fetches as a safety measure.
 fn progress_text(page_number: usize) -> String {
-    format!("Retrieving{}", ".".repeat(page_number))
+    format!("Retrieving Anthropic{}", ".".repeat(page_number))
 }
 
 // Junkyard.
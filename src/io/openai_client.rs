@@ -0,0 +1,5 @@
+pub mod client;
+pub mod dtos;
+
+pub use self::client::*;
+pub use self::dtos::*;
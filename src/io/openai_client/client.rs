@@ -0,0 +1,132 @@
+use jiff::Zoned;
+
+use super::dtos::{BucketByTime, ResponsePage};
+use crate::app::App;
+use crate::io::response::{REQUEST_TIMEOUT, try_read_response};
+use crate::io::retry::send_with_retry;
+use crate::prelude::*;
+
+/// How the spinner and the errors call it.
+pub const PROVIDER_NAME: &str = "OpenAI";
+/// Where a 429 sends the user.
+const RATE_LIMIT_DOCS: &str = "https://platform.openai.com/docs/guides/rate-limits";
+const BUCKET_WIDTH: &str = "1h";
+// The maximum number of buckets the endpoint accepts for the hourly width.
+const BUCKET_LIMIT: &str = "168";
+// Appended to `--openai-base-url`, which ends with `/v1` like in OpenAI's own SDKs.
+const USAGE_REPORT_PATH: &str = "/organization/usage/completions";
+const GAP_TIME_BETWEEN_FETCH_IN_SEC: u64 = 1;
+
+pub fn fetch(
+    ctx: &App,
+    key: &str,
+    base_url: &str,
+    starting_at: &Zoned,
+    ending_at: Option<&Zoned>,
+) -> AppResult<Vec<BucketByTime>> {
+    // Unlike Anthropic, this API expects unix seconds.
+    let start_time = starting_at.timestamp().as_second().to_string();
+    let end_time = ending_at.map(|time| time.timestamp().as_second().to_string());
+
+    // Same dance as the Anthropic client, see there.
+    let mut has_more: bool = true;
+    let mut page_number = 1;
+    let mut next_page: Option<String> = None;
+    let mut usages: Vec<BucketByTime> = vec![];
+
+    while has_more {
+        ctx.display
+            .update_progress(PROVIDER_NAME, progress_text(page_number));
+
+        if page_number > 1 {
+            wait();
+        }
+
+        let body = inner_fetch(
+            ctx,
+            key,
+            base_url,
+            &start_time,
+            end_time.as_deref(),
+            next_page.as_deref(),
+        )?;
+
+        usages.extend(body.data);
+
+        has_more = body.has_more;
+        next_page = body.next_page;
+        page_number += 1;
+    }
+
+    Ok(usages)
+}
+
+fn inner_fetch(
+    ctx: &App,
+    key: &str,
+    base_url: &str,
+    start_time: &str,
+    end_time: Option<&str>,
+    next_page: Option<&str>,
+) -> AppResult<ResponsePage> {
+    let endpoint = usage_report_endpoint(base_url);
+
+    // Built from scratch on every attempt, a request can only be sent once.
+    let send = || {
+        let request = ureq::get(&endpoint)
+            .header("Authorization", format!("Bearer {}", key))
+            // ranging, sizing.
+            .query("start_time", start_time)
+            .query("bucket_width", BUCKET_WIDTH)
+            .query("limit", BUCKET_LIMIT)
+            // grouping.
+            .query("group_by", "model")
+            .query("group_by", "project_id")
+            .query("group_by", "api_key_id")
+            .query("group_by", "service_tier")
+            .query("group_by", "batch");
+
+        // optional page.
+        let request = match next_page {
+            Some(page_token) => request.query("page", page_token),
+            None => request,
+        };
+
+        // optional end_time.
+        let request = match end_time {
+            Some(timestamp) => request.query("end_time", timestamp),
+            None => request,
+        };
+
+        // Statuses are handled below, and a 429 has to keep its retry-after.
+        request
+            .config()
+            .http_status_as_error(false)
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .call()
+    };
+
+    try_read_response(
+        PROVIDER_NAME,
+        &endpoint,
+        send_with_retry(ctx, PROVIDER_NAME, send),
+        RATE_LIMIT_DOCS,
+    )
+}
+
+/// Same as the Anthropic client, a trailing slash is fine.
+fn usage_report_endpoint(base_url: &str) -> String {
+    format!("{}{}", base_url.trim_end_matches('/'), USAGE_REPORT_PATH)
+}
+
+/// OpenAI is more generous with its rate limit, but let's still be polite.
+fn wait() {
+    let duration = std::time::Duration::from_secs(GAP_TIME_BETWEEN_FETCH_IN_SEC);
+
+    std::thread::sleep(duration);
+}
+
+fn progress_text(page_number: usize) -> String {
+    format!("Retrieving OpenAI{}", ".".repeat(page_number))
+}
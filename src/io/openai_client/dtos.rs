@@ -0,0 +1,84 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+// API Reference: https://platform.openai.com/docs/api-reference/usage/completions
+
+/// Response from the endpoint, paged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponsePage {
+    /// A list of usage data buckets.
+    pub data: Vec<BucketByTime>,
+
+    /// Indicates if there are more results available.
+    pub has_more: bool,
+
+    /// Cursor to provide in as page in the subsequent request to retrieve the next page of data.
+    pub next_page: Option<String>,
+}
+
+/// The response page body, partitioned by time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketByTime {
+    /// Inclusive - include this very exact moment, in unix seconds.
+    pub start_time: i64,
+
+    /// Exclusive - not include, in unix seconds.
+    pub end_time: i64,
+
+    /// List of usage items for this time bucket. The real work is inside it.
+    pub results: Vec<UsageEntry>,
+}
+
+/// Represents a single completions usage aggregation result.
+///
+/// Fields corresponding to grouping parameters (like `model`, `api_key_id`, etc.)
+/// will be `None` if that specific grouping was not requested.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct UsageEntry {
+    /// The number of input tokens used, including cached tokens.
+    pub input_tokens: u64,
+
+    /// The number of input tokens that were read from the cache.
+    #[serde(default)]
+    pub input_cached_tokens: u64,
+
+    /// The number of output tokens used.
+    pub output_tokens: u64,
+
+    /// The number of audio input tokens used, including cached tokens.
+    #[serde(default)]
+    pub input_audio_tokens: u64,
+
+    /// The number of audio output tokens used.
+    #[serde(default)]
+    pub output_audio_tokens: u64,
+
+    /// The count of requests made to the model.
+    #[serde(default)]
+    pub num_model_requests: u64,
+
+    /// ID of the project used. Null if not grouping by project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+
+    /// ID of the user. Null if not grouping by user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+
+    /// ID of the API key used. Null if not grouping by API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<String>,
+
+    /// Model name used. Null if not grouping by model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Whether the usage came from the Batch API. Null if not grouping by batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<bool>,
+
+    /// Service tier used (e.g., "default", "flex"). Null if not grouping by service tier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+}
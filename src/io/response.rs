@@ -0,0 +1,133 @@
+//! Turns what the provider APIs answer into data or a diagnostic.
+//!
+//! Every client fails the same ways, only where the rate limit docs are is their own.
+
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use ureq::Body;
+use ureq::http::Response;
+
+use crate::error::Error;
+use crate::prelude::*;
+
+/// For the whole request, reading the body included.
+/// Without one, a stuck connection would hang the status bar forever.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How much of a response body ends up in a diagnostic, in characters.
+const BODY_EXCERPT_LENGTH: usize = 300;
+
+/// Reads a successful response as `T`, or maps the failure to its `meter::api::*` error.
+///
+/// `rate_limit_docs` is where a 429 sends the user, after the retries gave up.
+pub fn try_read_response<T: DeserializeOwned>(
+    provider_name: &str,
+    endpoint: &str,
+    result: Result<Response<Body>, ureq::Error>,
+    rate_limit_docs: &str,
+) -> AppResult<T> {
+    let provider = provider_name.to_owned();
+
+    let mut response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Timeout(_)) => bail!(Error::ApiTimeout { provider }),
+        Err(e) if is_dns_failure(&e) => bail!(Error::ApiHostNotFound {
+            endpoint: endpoint.to_owned(),
+        }),
+        Err(e @ (ureq::Error::Io(_) | ureq::Error::ConnectionFailed)) => {
+            bail!(Error::ApiUnreachable {
+                provider,
+                reason: e.to_string(),
+            })
+        }
+        Err(e) => bail!(e),
+    };
+
+    let status = response.status().as_u16();
+
+    // Error bodies are usually a short JSON message, and the best hint we have.
+    let body = match response.body_mut().read_to_string() {
+        Ok(body) => body,
+        // A body that can't be read is still worth the status, unless it's the data we came for.
+        Err(_) if !response.status().is_success() => String::new(),
+        Err(ureq::Error::Timeout(_)) => bail!(Error::ApiTimeout { provider }),
+        Err(e) => bail!(e),
+    };
+
+    match status {
+        200..=299 => {}
+        401 => bail!(Error::ApiUnauthorized {
+            provider,
+            body: excerpt(&body, 0),
+        }),
+        403 => bail!(Error::ApiForbidden {
+            provider,
+            body: excerpt(&body, 0),
+        }),
+        404 => bail!(Error::ApiEndpointNotFound {
+            provider,
+            endpoint: endpoint.to_owned(),
+            body: excerpt(&body, 0),
+        }),
+        429 => bail!(Error::ApiRateLimited {
+            provider,
+            docs_url: rate_limit_docs.to_owned(),
+        }),
+        status => bail!(Error::ApiUnexpectedStatus {
+            provider,
+            status,
+            body: excerpt(&body, 0),
+        }),
+    }
+
+    serde_json::from_str(&body).map_err(|e| {
+        // Show the part that didn't fit, not the start of a page of buckets.
+        let offset = miette::SourceOffset::from_location(&body, e.line(), e.column()).offset();
+
+        Error::ApiUnexpectedSchema {
+            provider,
+            reason: e.to_string(),
+            body: excerpt(&body, offset),
+        }
+        .into()
+    })
+}
+
+/// The host doesn't resolve, no point in trying again.
+///
+/// ureq only says so itself when the lookup comes back empty. When the lookup fails, it's an
+/// I/O error like any other, and std only tells them apart by the message.
+pub fn is_dns_failure(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::HostNotFound => true,
+        ureq::Error::Io(e) => e.to_string().contains("failed to lookup address"),
+        _ => false,
+    }
+}
+
+/// Up to `BODY_EXCERPT_LENGTH` characters of the body, roughly centered on `offset`.
+fn excerpt(body: &str, offset: usize) -> String {
+    let body = body.trim();
+
+    if body.is_empty() {
+        return "(empty)".to_owned();
+    }
+
+    let start = offset.saturating_sub(BODY_EXCERPT_LENGTH / 2);
+    let characters = body.char_indices().skip_while(|(index, _)| *index < start);
+
+    let text: String = characters
+        .map(|(_, character)| character)
+        .take(BODY_EXCERPT_LENGTH)
+        .collect();
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if start + text.len() < body.len() {
+        "…"
+    } else {
+        ""
+    };
+
+    format!("{prefix}{text}{suffix}")
+}
@@ -0,0 +1,108 @@
+//! Retries for the provider clients.
+//!
+//! Rate limits and overloaded servers are a normal part of paging through a usage report,
+//! so a single bad answer shouldn't throw away the pages we already have.
+//! Everything else fails right away, retrying a wrong key won't make it right.
+
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::time::Duration;
+
+use jiff::Timestamp;
+use ureq::Body;
+use ureq::http::Response;
+
+use super::response::is_dns_failure;
+use crate::app::App;
+
+/// Rate limited, internal error, overloaded.
+const RETRYABLE_STATUSES: [u16; 3] = [429, 500, 529];
+
+/// The first backoff, doubled on every attempt up to `MAX_BACKOFF`.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(32);
+
+/// A `retry-after` longer than this isn't worth waiting for, we give up instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Sends a request until it gets an answer worth keeping, or runs out of attempts.
+///
+/// `send` is called once per attempt, so it has to build the request every time.
+/// The request should not treat HTTP statuses as errors, otherwise `retry-after` is lost.
+///
+/// Returns the last response, whatever its status, or the last connection error.
+/// Telling a 200 from a 401 is still the caller's job.
+pub fn send_with_retry(
+    ctx: &App,
+    provider_name: &str,
+    send: impl Fn() -> Result<Response<Body>, ureq::Error>,
+) -> Result<Response<Body>, ureq::Error> {
+    let max_attempts = ctx.cli.max_attempts;
+    let mut attempt = 1;
+
+    loop {
+        let result = send();
+
+        let (reason, retry_after) = match &result {
+            Ok(response) if RETRYABLE_STATUSES.contains(&response.status().as_u16()) => (
+                format!("HTTP {}", response.status().as_u16()),
+                parse_retry_after(response),
+            ),
+            Err(e) if is_dns_failure(e) => return result,
+            Err(ureq::Error::Io(_) | ureq::Error::ConnectionFailed | ureq::Error::Timeout(_)) => {
+                ("connection error".to_owned(), None)
+            }
+            _ => return result,
+        };
+
+        let delay = match retry_after {
+            Some(delay) if delay > MAX_RETRY_AFTER => return result,
+            Some(delay) => delay,
+            None => backoff(attempt),
+        };
+
+        if attempt >= max_attempts {
+            return result;
+        }
+
+        ctx.display.update_progress(
+            provider_name,
+            format!(
+                "{provider_name}: {reason}, retrying in {}s ({}/{max_attempts})",
+                delay.as_secs_f64().ceil(),
+                attempt + 1,
+            ),
+        );
+
+        std::thread::sleep(delay);
+
+        attempt += 1;
+    }
+}
+
+/// Exponential, with full jitter in the upper half so a few processes hitting the same limit
+/// don't come back at the very same moment.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_BACKOFF);
+
+    // Every `RandomState` is seeded differently, that's all the randomness this needs.
+    let random = RandomState::new().build_hasher().finish();
+    let fraction = 0.5 + (random as f64 / u64::MAX as f64) / 2.0;
+
+    ceiling.mul_f64(fraction)
+}
+
+/// `retry-after` is either a number of seconds or an HTTP date.
+fn parse_retry_after(response: &Response<Body>) -> Option<Duration> {
+    let value = response.headers().get("retry-after")?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+
+    let until = jiff::fmt::rfc2822::parse(value).ok()?.timestamp();
+    let delay = until.duration_since(Timestamp::now());
+
+    Some(Duration::try_from(delay).unwrap_or_default())
+}
@@ -0,0 +1,82 @@
+//! Where usage comes from.
+//!
+//! Every provider is a `UsageSource`, a self-contained module under `source/` that knows its
+//! flags, its key, its API, and how to turn what the API says into unified buckets.
+//! Main only ever walks the registry, so adding a provider means adding a module and a line
+//! to `SOURCES`, plus its `Provider` variant and a flatten of its flags in `Cli`.
+
+mod anthropic;
+mod openai;
+
+pub use self::anthropic::AnthropicArgs;
+pub use self::openai::OpenaiArgs;
+
+use itertools::Itertools;
+use jiff::Zoned;
+
+use self::anthropic::AnthropicSource;
+use self::openai::OpenaiSource;
+use super::unified_dtos::UnifiedBucketByTime;
+use crate::app::App;
+use crate::cli::{Cli, Provider};
+use crate::error::Error;
+use crate::prelude::*;
+
+/// Every supported provider, in the order they are fetched and listed.
+static SOURCES: [&dyn UsageSource; 2] = [&AnthropicSource, &OpenaiSource];
+
+pub trait UsageSource: Sync {
+    /// The provider, as `--provider` and the reports call it.
+    fn provider(&self) -> Provider;
+
+    /// The key from the CLI or the environment, if there is one.
+    fn api_key<'a>(&self, cli: &'a Cli) -> Option<&'a str>;
+
+    /// What to tell the user when they asked for this provider without a key.
+    fn missing_key_error(&self) -> Error;
+
+    /// Where the API lives. A part of the data cache key, so a mock never mixes with the real one.
+    fn base_url<'a>(&self, cli: &'a Cli) -> &'a str;
+
+    /// Fetches the usage of `[start, end)` and converts it, `None` meaning now.
+    fn fetch_unified(
+        &self,
+        ctx: &App,
+        key: &str,
+        start: &Zoned,
+        end: Option<&Zoned>,
+    ) -> AppResult<Vec<UnifiedBucketByTime>>;
+}
+
+/// A validated source and its required API key.
+/// This is used to control the main logic, determining which source to dispatch fetch to.
+pub type SourceKeyPair = (&'static dyn UsageSource, String);
+
+/// Loads API keys for the sources based on user selection.
+///
+/// If the user explicitly chose providers, this returns an error if any are missing keys.
+/// If no providers were specified, it returns all sources that have keys available
+/// and ignores those that don't.
+pub fn load_sources(cli: &Cli) -> AppResult<Vec<SourceKeyPair>> {
+    let with_key = |source: &&'static dyn UsageSource| {
+        source.api_key(cli).map(|key| (*source, key.to_owned()))
+    };
+
+    match &cli.provider {
+        // Strict mode: user specified providers, error if keys missing.
+        Some(user_selected) => {
+            let user_selected = user_selected.iter().unique().collect_vec();
+
+            SOURCES
+                .iter()
+                .filter(|source| user_selected.contains(&&source.provider()))
+                .map(|source| with_key(source).ok_or_else(|| source.missing_key_error().into()))
+                .collect()
+        }
+
+        // Auto mode: No providers specified by user.
+        // Return all sources that have API keys available.
+        // Silently skip sources without keys.
+        None => Ok(SOURCES.iter().filter_map(with_key).collect()),
+    }
+}
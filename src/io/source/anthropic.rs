@@ -0,0 +1,130 @@
+use jiff::Zoned;
+
+use super::UsageSource;
+use crate::app::App;
+use crate::cli::{Cli, Provider};
+use crate::error::Error;
+use crate::io::claude_client::{self, BucketByTime, PROVIDER_NAME, UsageEntry};
+use crate::io::unified_dtos::{UnifiedBucketByTime, UnifiedCacheCreation, UnifiedUsageEntry};
+use crate::prelude::*;
+
+const KEY_ENV_VAR: &str = "ANTHROPIC_ADMIN_API_KEY";
+const KEY_DOCS: &str = "https://platform.claude.com/docs/en/build-with-claude/administration-api";
+
+/// The Admin API usage report, `/v1/organizations/usage_report/messages`.
+pub struct AnthropicSource;
+
+/// The flags of this source, flattened into `Cli`.
+#[derive(clap::Args, Debug, Serialize)]
+pub struct AnthropicArgs {
+    #[arg(long, env = KEY_ENV_VAR, hide_env_values = true, global = true)]
+    pub anthropic_admin_api_key: Option<String>,
+
+    /// Where the Anthropic API lives, for a proxy or a local mock server.
+    /// The admin key is sent there, so only point it at something you trust.
+    ///
+    /// Not read from ANTHROPIC_BASE_URL, that one often points the SDKs at a third-party
+    /// gateway, which has no business seeing an admin key.
+    #[arg(
+        long,
+        env = "METER_ANTHROPIC_BASE_URL",
+        default_value = "https://api.anthropic.com",
+        global = true
+    )]
+    pub anthropic_base_url: String,
+}
+
+impl UsageSource for AnthropicSource {
+    fn provider(&self) -> Provider {
+        Provider::Anthropic
+    }
+
+    fn api_key<'a>(&self, cli: &'a Cli) -> Option<&'a str> {
+        cli.anthropic.anthropic_admin_api_key.as_deref()
+    }
+
+    fn missing_key_error(&self) -> Error {
+        Error::ApiKeyNotFound {
+            provider: PROVIDER_NAME.to_owned(),
+            env_var: KEY_ENV_VAR.to_owned(),
+            docs_url: KEY_DOCS.to_owned(),
+        }
+    }
+
+    fn base_url<'a>(&self, cli: &'a Cli) -> &'a str {
+        &cli.anthropic.anthropic_base_url
+    }
+
+    fn fetch_unified(
+        &self,
+        ctx: &App,
+        key: &str,
+        start: &Zoned,
+        end: Option<&Zoned>,
+    ) -> AppResult<Vec<UnifiedBucketByTime>> {
+        let anthropic_usages = claude_client::fetch(ctx, key, self.base_url(&ctx.cli), start, end)?;
+
+        unify_from_anthropic(anthropic_usages)
+    }
+}
+
+/// Converts a collection of Anthropic-specific usage buckets into a unified format.
+fn unify_from_anthropic(
+    anthropic_buckets: Vec<BucketByTime>,
+) -> AppResult<Vec<UnifiedBucketByTime>> {
+    anthropic_buckets
+        .into_iter()
+        .map(UnifiedBucketByTime::try_from)
+        .collect()
+}
+
+/// Maps a raw Anthropic usage entry to my unified version of it.
+impl From<UsageEntry> for UnifiedUsageEntry {
+    fn from(entry: UsageEntry) -> Self {
+        UnifiedUsageEntry {
+            model: entry.model,
+            context_window: entry.context_window,
+            workspace_id: entry.workspace_id,
+            api_key_id: entry.api_key_id,
+            service_tier: entry.service_tier,
+            cache_read_input_tokens: entry.cache_read_input_tokens,
+            cache_creation: UnifiedCacheCreation {
+                ephemeral_1h_input_tokens: entry.cache_creation.ephemeral_1h_input_tokens,
+                ephemeral_5m_input_tokens: entry.cache_creation.ephemeral_5m_input_tokens,
+            },
+            uncached_input_tokens: entry.uncached_input_tokens,
+            output_tokens: entry.output_tokens,
+        }
+    }
+}
+
+/// Try to transform an Anthropic bucket into my unified bucket.
+impl TryFrom<BucketByTime> for UnifiedBucketByTime {
+    type Error = miette::Report;
+
+    fn try_from(bucket: BucketByTime) -> Result<Self, Self::Error> {
+        let provider = Provider::Anthropic;
+        let start = bucket
+            .starting_at
+            .parse::<jiff::Timestamp>()
+            .into_diagnostic()?
+            .as_second();
+        let end = bucket
+            .ending_at
+            .parse::<jiff::Timestamp>()
+            .into_diagnostic()?
+            .as_second();
+        let results = bucket
+            .results
+            .into_iter()
+            .map(UnifiedUsageEntry::from)
+            .collect();
+
+        Ok(Self {
+            provider,
+            start,
+            end,
+            results,
+        })
+    }
+}
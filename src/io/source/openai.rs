@@ -0,0 +1,129 @@
+use jiff::Zoned;
+
+use super::UsageSource;
+use crate::app::App;
+use crate::cli::{Cli, Provider};
+use crate::error::Error;
+use crate::io::openai_client::{self, PROVIDER_NAME};
+use crate::io::unified_dtos::{UnifiedBucketByTime, UnifiedCacheCreation, UnifiedUsageEntry};
+use crate::prelude::*;
+
+const KEY_ENV_VAR: &str = "OPENAI_ADMIN_API_KEY";
+const KEY_DOCS: &str = "https://platform.openai.com/settings/organization/admin-keys";
+
+/// The organization usage API for completions, `/organization/usage/completions`.
+pub struct OpenaiSource;
+
+/// The flags of this source, flattened into `Cli`.
+#[derive(clap::Args, Debug, Serialize)]
+pub struct OpenaiArgs {
+    #[arg(long, env = KEY_ENV_VAR, hide_env_values = true, global = true)]
+    pub openai_admin_api_key: Option<String>,
+
+    /// Where the OpenAI API lives, for a proxy or a local mock server.
+    /// The admin key is sent there, so only point it at something you trust.
+    ///
+    /// Not read from OPENAI_BASE_URL, that one often points the SDKs at a third-party
+    /// gateway, which has no business seeing an admin key.
+    #[arg(
+        long,
+        env = "METER_OPENAI_BASE_URL",
+        default_value = "https://api.openai.com/v1",
+        global = true
+    )]
+    pub openai_base_url: String,
+}
+
+impl UsageSource for OpenaiSource {
+    fn provider(&self) -> Provider {
+        Provider::Openai
+    }
+
+    fn api_key<'a>(&self, cli: &'a Cli) -> Option<&'a str> {
+        cli.openai.openai_admin_api_key.as_deref()
+    }
+
+    fn missing_key_error(&self) -> Error {
+        Error::ApiKeyNotFound {
+            provider: PROVIDER_NAME.to_owned(),
+            env_var: KEY_ENV_VAR.to_owned(),
+            docs_url: KEY_DOCS.to_owned(),
+        }
+    }
+
+    fn base_url<'a>(&self, cli: &'a Cli) -> &'a str {
+        &cli.openai.openai_base_url
+    }
+
+    fn fetch_unified(
+        &self,
+        ctx: &App,
+        key: &str,
+        start: &Zoned,
+        end: Option<&Zoned>,
+    ) -> AppResult<Vec<UnifiedBucketByTime>> {
+        let openai_usages = openai_client::fetch(ctx, key, self.base_url(&ctx.cli), start, end)?;
+
+        unify_from_openai(openai_usages)
+    }
+}
+
+/// Converts a collection of OpenAI-specific usage buckets into a unified format.
+fn unify_from_openai(
+    openai_buckets: Vec<openai_client::BucketByTime>,
+) -> AppResult<Vec<UnifiedBucketByTime>> {
+    let unified = openai_buckets
+        .into_iter()
+        .map(UnifiedBucketByTime::from)
+        .collect();
+
+    Ok(unified)
+}
+
+/// Maps a raw OpenAI usage entry to my unified version of it.
+///
+/// OpenAI counts cached tokens as part of `input_tokens`, so they have to be taken out
+/// to match the Anthropic meaning of "uncached".
+///
+/// OpenAI doesn't report batches as a service tier, but as a flag of their own. A batch becomes
+/// the "batch" tier here, like on Anthropic, so it's grouped and priced the same way.
+impl From<openai_client::UsageEntry> for UnifiedUsageEntry {
+    fn from(entry: openai_client::UsageEntry) -> Self {
+        let service_tier = match entry.batch {
+            Some(true) => Some("batch".to_owned()),
+            _ => entry.service_tier,
+        };
+
+        UnifiedUsageEntry {
+            model: entry.model,
+            context_window: None,           // OpenAI doesn't report it.
+            workspace_id: entry.project_id, // The closest thing OpenAI has to a workspace.
+            api_key_id: entry.api_key_id,
+            service_tier,
+            cache_read_input_tokens: entry.input_cached_tokens,
+            uncached_input_tokens: entry.input_tokens.saturating_sub(entry.input_cached_tokens),
+            // OpenAI caches automatically and doesn't charge for the writes.
+            cache_creation: UnifiedCacheCreation::default(),
+            output_tokens: entry.output_tokens,
+        }
+    }
+}
+
+/// Transform an OpenAI bucket into my unified bucket.
+/// This one can't fail, the timestamps are already in unix seconds.
+impl From<openai_client::BucketByTime> for UnifiedBucketByTime {
+    fn from(bucket: openai_client::BucketByTime) -> Self {
+        let results = bucket
+            .results
+            .into_iter()
+            .map(UnifiedUsageEntry::from)
+            .collect();
+
+        Self {
+            provider: Provider::Openai,
+            start: bucket.start_time,
+            end: bucket.end_time,
+            results,
+        }
+    }
+}
@@ -28,26 +28,29 @@ pub struct UnifiedUsageEntry {
     /// The number of input tokens read from the cache.
     pub cache_read_input_tokens: u64,
 
-    // Breakdown of tokens used for cache creation.
-    // pub cache_creation: CacheCreationUsage,
+    /// Breakdown of tokens used for cache creation.
+    pub cache_creation: UnifiedCacheCreation,
+
     /// The number of output tokens generated.
     pub output_tokens: u64,
 
-    // ID of the API key used. Null if not grouping by API key.
-    // #[serde(skip_serializing_if = "Option::is_none")]
-    // pub api_key_id: Option<String>,
+    /// ID of the API key used. Null if not grouping by API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<String>,
 
     // Model name used. Null if not grouping by model.
     // #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
 
-    // ID of the Workspace used. Null if not grouping by workspace.
-    // #[serde(skip_serializing_if = "Option::is_none")]
-    // pub workspace_id: Option<String>,
+    /// ID of the Workspace used, or the project for OpenAI.
+    /// Null if not grouping by workspace, or for the default workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
 
-    // Service tier used (e.g., "standard", "batch"). Null if not grouping by service tier.
-    // #[serde(skip_serializing_if = "Option::is_none")]
-    // pub service_tier: Option<String>,
+    /// Service tier used (e.g., "standard", "batch", "flex"). Null if not grouping by service tier.
+    /// OpenAI batches are "batch" too, whatever tier OpenAI says they ran in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
 
     // Context window size used (e.g., "0-200k", "200k-1M"). Null if not grouping by context window.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -63,13 +66,38 @@ pub struct UnifiedUsageEntryCollapsed {
     /// The number of input tokens read from the cache.
     pub cache_read_input_tokens: u64,
 
+    /// Breakdown of tokens used for cache creation.
+    pub cache_creation: UnifiedCacheCreation,
+
     /// The number of output tokens generated.
     pub output_tokens: u64,
 
     /// Model name used.
     pub model: String,
 
+    /// The total cost in dollars.
+    /// Priced entry by entry before collapsing, since each one may have its own rate.
+    pub cost: f64,
+
     // Context window size used (e.g., "0-200k", "200k-1M"). Null if not grouping by context window.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context_window: Option<String>,
 }
+
+/// Detailed breakdown of cache creation tokens.
+/// Each duration is priced differently, so they can't be merged into one number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct UnifiedCacheCreation {
+    /// The number of input tokens used to create a 1-hour cache entry.
+    pub ephemeral_1h_input_tokens: u64,
+
+    /// The number of input tokens used to create a 5-minute cache entry.
+    pub ephemeral_5m_input_tokens: u64,
+}
+
+impl UnifiedCacheCreation {
+    /// All cache creation tokens, regardless of their duration.
+    pub fn total_input_tokens(&self) -> u64 {
+        self.ephemeral_1h_input_tokens + self.ephemeral_5m_input_tokens
+    }
+}
@@ -8,38 +8,74 @@ mod io;
 mod prelude;
 mod router;
 
-use jiff::{Span, Zoned};
-use std::hash::Hasher;
-use twox_hash::XxHash64;
+use std::process::ExitCode;
 
-use cli::{Cli, Provider};
-use io::claude_client::BucketByTime;
+use jiff::{RoundMode, Span, Timestamp, Unit, Zoned, ZonedRound};
+
+use calculation::usage_report::UsageReport;
+use cli::{CacheAction, CacheArgs, Cli, Commands, Period, SumArgs, TimeBound};
+use error::Error;
+use io::source::SourceKeyPair;
 use prelude::*;
 
 use self::io::unified_dtos::UnifiedBucketByTime;
 
-fn main() -> AppResult<()> {
+/// How long to wait for another instance that is already fetching the same thing.
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
+/// Appended to an expired result returned by `--stale-ok`.
+const STALE_MARKER: &str = "⟳";
+
+/// What `--partial` exits with when some providers are missing from the output.
+/// Errors exit with 1, like any miette report.
+const PARTIAL_EXIT_CODE: u8 = 3;
+
+/// How long a partial output is cached, at most.
+/// Long enough to spare the failing provider every tick, short enough to notice it's back.
+const PARTIAL_TTL_MINUTES: i64 = 1;
+
+fn main() -> AppResult<ExitCode> {
     let cli = Cli::new();
-    let providers = cli.load_providers()?;
-    let app = app::App::new(cli);
-    let args_signature = create_args_signature(&app.cli)?;
-    let cache_file_path = create_cache_file_path(&args_signature)?;
+    let time_zone = cli.try_get_time_zone()?;
+
+    // Housekeeping, no provider or price needed.
+    if let Commands::Cache(CacheArgs { action }) = &cli.command {
+        run_cache_action(action, &Timestamp::now().to_zoned(time_zone))?;
+
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let sources = io::source::load_sources(&cli)?;
+    let pricing = config::pricing_file::try_load_pricing(cli.pricing_file.as_deref())?;
+    let app = app::App::new(cli, pricing, time_zone);
 
     // Use this to make an api call, it has to be aligned with my time.
-    let zoned_now = Zoned::now();
+    // "My time" being --tz, so a UTC box can still report in the team's timezone.
+    let zoned_now = Timestamp::now().to_zoned(app.time_zone.clone());
 
     // System time is a naked utc time.
     // So, we have to convert it back to the utc.
     let system_now = &zoned_now.in_tz("UTC").into_diagnostic()?.timestamp();
 
+    // Resolved before the cache lookup, because the range is a part of the cache key.
+    let (report_start, report_end) = resolve_report_range(&app.cli, &zoned_now)?;
+
+    let args_signature = create_args_signature(&app.cli, &report_start, report_end.as_ref())?;
+    let cache_file_path = io::cache::create_cache_file_path(&args_signature)?;
+
     let ttl_minutes: i64 = app.cli.ttl_minutes;
 
-    let output_message: String =
+    // Why some providers are missing from the output, with --partial.
+    // Only known on a refresh, a cached partial output only says which ones.
+    let mut partial_errors = vec![];
+
+    let output: io::cache::CachedOutput =
         match io::cache::try_retrieve_cache(&cache_file_path, &ttl_minutes, system_now) {
             // Cache hit. The content is ready to use.
-            Ok(Some(cached_string)) => cached_string,
+            Ok(Some(cached_output)) => cached_output,
 
-            // Cache failed to load somehow.
+            // Cache failed to load somehow, the file is there but can't be read.
+            // A corrupt or outdated entry is only a miss, this is worse than that.
             //
             // This program must not be run without a cache.
             //
@@ -60,97 +96,597 @@ fn main() -> AppResult<()> {
                 );
             }
 
-            // No cache, expired, or doesn't exist, so it's okay to refresh.
-            // The actual application logic happens here.
-            Ok(None) => {
-                app.display.maybe_start_spin();
-
-                let days_ago = app.cli.try_parse_since()? as i64;
-                let report_start = calculate_start_date(&zoned_now, days_ago)?;
-
-                // Keep the higher-level logic symmetric.
-                // This makes it easy to swap the closure body for thread spawning later.
-                // If I can live with the messiness inside this function, I will go with it.
-                let joined_results = providers
-                    .iter()
-                    .map(|(provider, _)| try_fetch_unified(&app, &report_start, provider))
-                    .collect::<AppResult<Vec<Vec<_>>>>()?
-                    .into_iter()
-                    .flatten()
-                    .collect();
-
-                let report = router::does_the_thing(&app, joined_results)?;
-
-                report.render(app.cli.unformatted, None)?
+            // Expired, but the user would rather have the old answer now than the new one later.
+            // Someone else refreshes it, see `spawn_background_refresh`.
+            Ok(None)
+                if app.cli.stale_ok
+                    && let Some(stale_output) =
+                        io::cache::try_retrieve_stale_cache(&cache_file_path)? =>
+            {
+                spawn_background_refresh(&cache_file_path)?;
+
+                io::cache::CachedOutput {
+                    payload: mark_as_stale(&app.cli, stale_output.payload)?,
+                    ..stale_output
+                }
             }
+
+            // No cache, expired, or doesn't exist, so it's okay to refresh.
+            //
+            // On a cold cache, every instance ends up here at the same time. Only the one
+            // holding the lock refreshes, the others wait for it, then read what it wrote.
+            Ok(None) => match io::cache::try_acquire_lock(&cache_file_path, LOCK_TIMEOUT)? {
+                Some(_lock) => {
+                    // Somebody else may have refreshed it while we were waiting.
+                    match io::cache::try_retrieve_cache(&cache_file_path, &ttl_minutes, system_now)?
+                    {
+                        Some(cached_output) => cached_output,
+                        None => {
+                            let report = refresh_report(
+                                &app,
+                                &sources,
+                                &report_start,
+                                report_end.as_ref(),
+                                system_now,
+                            )?;
+                            let output_message = report.render(app.cli.unformatted, None)?;
+
+                            // Cached too, or every waiting pane and every tick would ask the
+                            // failing provider again. Only briefly, it may be back soon.
+                            let (partial, ttl_minutes) = match report {
+                                UsageReport::Partial { errors, .. } => {
+                                    partial_errors = errors;
+
+                                    (true, ttl_minutes.min(PARTIAL_TTL_MINUTES))
+                                }
+                                _ => (false, ttl_minutes),
+                            };
+
+                            // A simple way to check the output validity, for now.
+                            if !output_message.is_empty() {
+                                let meta = io::cache::CacheMeta {
+                                    command: describe_command(),
+                                    report_start: report_start.timestamp(),
+                                    report_end: report_end.as_ref().map(Zoned::timestamp),
+                                    ttl_minutes,
+                                    partial,
+                                };
+
+                                io::cache::try_write_cache(
+                                    &cache_file_path,
+                                    &output_message,
+                                    &meta,
+                                    &ttl_minutes,
+                                    system_now,
+                                )?;
+                            }
+
+                            io::cache::CachedOutput {
+                                payload: output_message,
+                                partial,
+                            }
+                        }
+                    }
+                }
+
+                // Waited long enough, the last answer will have to do.
+                None => io::cache::try_retrieve_stale_cache(&cache_file_path)?
+                    .ok_or(Error::CacheLockTimeout)?,
+            },
         };
 
-    // A simple way to check the output validity, for now.
-    if !output_message.is_empty() {
-        io::cache::try_write_cache(&cache_file_path, &output_message, &ttl_minutes, system_now)?;
+    app.display.stop_spin_with_message(&output.payload);
+
+    if !output.partial {
+        return Ok(ExitCode::SUCCESS);
     }
 
-    app.display.stop_spin_with_message(&output_message);
+    // The output is for the status bar, the reasons for whoever is looking at stderr.
+    for e in partial_errors {
+        eprintln!("{e:?}");
+    }
 
-    Ok(())
+    Ok(ExitCode::from(PARTIAL_EXIT_CODE))
 }
 
 // private
+
+/// Runs this very command again as a detached process, without `--stale-ok`, so it refreshes
+/// the cache the usual way and exits. Nobody waits for it.
+///
+/// Skipped if another process is already refreshing the same cache, otherwise a status bar
+/// would pile up one of these every tick until the first one is done.
+/// The lock is held until the child is started, which then waits for it like any other run.
+///
+/// No need for `--no-animate`, the child has no terminal, so it has no spinner either.
+/// It can't be added anyway, clap rejects it when the command already has it.
+fn spawn_background_refresh(cache_file_path: &std::path::Path) -> AppResult<()> {
+    use std::process::{Command, Stdio};
+
+    let Some(_lock) = io::cache::try_acquire_lock(cache_file_path, std::time::Duration::ZERO)?
+    else {
+        return Ok(());
+    };
+
+    let args = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--stale-ok");
+
+    Command::new(std::env::current_exe().into_diagnostic()?)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .into_diagnostic()
+        .wrap_err("Could not start the background refresh.")?;
+
+    Ok(())
+}
+
+/// Lets a status bar tell an old answer from a fresh one.
+/// A single value gets it on the same line, a table gets a row of its own, shaped like the rest.
+/// `raw` is left alone, it has to stay valid JSON.
+fn mark_as_stale(cli: &Cli, output: String) -> AppResult<String> {
+    let marked = match &cli.command {
+        Commands::Raw | Commands::Cache(_) => output,
+        Commands::Sum(SumArgs {
+            group_by: Some(groupings),
+            ..
+        }) if !groupings.is_empty() => {
+            let footer = UsageReport::footer_csv(groupings.len(), &[STALE_MARKER.to_owned()])?;
+
+            format!("{output}{footer}")
+        }
+        Commands::Sum(_) => format!("{output} {STALE_MARKER}"),
+    };
+
+    Ok(marked)
+}
+
+/// `meter cache`, straight to the terminal.
+fn run_cache_action(action: &CacheAction, zoned_now: &Zoned) -> AppResult<()> {
+    let system_now = zoned_now.timestamp();
+
+    match action {
+        CacheAction::List => {
+            for entry in io::cache::try_list_entries()? {
+                let status = if entry.is_expired(&system_now) {
+                    "expired"
+                } else {
+                    "fresh"
+                };
+                let command = entry
+                    .envelope
+                    .as_ref()
+                    .map_or("?", |envelope| envelope.meta.command.as_str());
+
+                println!(
+                    "{:<26}  {:>10}  {:<7}  {:>9}  {}",
+                    entry.hash,
+                    format_age(&entry.created_at, &system_now)?,
+                    status,
+                    format_size(entry.size_in_bytes),
+                    command,
+                );
+            }
+
+            // The usage data the results are computed from, one store per provider and key.
+            for store in io::bucket_store::try_list_stores()? {
+                println!(
+                    "{:<26}  {:>10}  store    {:>9}  (usage buckets)",
+                    store.name,
+                    format_age(&store.modified_at, &system_now)?,
+                    format_size(store.size_in_bytes),
+                );
+            }
+        }
+
+        CacheAction::Show { hash } => {
+            let entry = io::cache::try_find_entry(hash)?
+                .ok_or_else(|| miette!("No cached result with the hash {hash}."))?;
+
+            let Some(envelope) = &entry.envelope else {
+                bail!("The cached result {hash} is corrupt, or from an older meter.");
+            };
+            let meta = &envelope.meta;
+
+            // In the --tz timezone, like every other time meter prints.
+            let zoned = |timestamp: Timestamp| timestamp.to_zoned(zoned_now.time_zone().clone());
+
+            println!("command:      {}", meta.command);
+            println!("report_start: {}", zoned(meta.report_start));
+            println!(
+                "report_end:   {}",
+                meta.report_end
+                    .map_or("now".to_owned(), |end| zoned(end).to_string())
+            );
+            println!("ttl_minutes:  {}", meta.ttl_minutes);
+            println!("partial:      {}", meta.partial);
+            println!("created_at:   {}", zoned(envelope.created_at));
+            println!("meter:        {}", envelope.meter_version);
+
+            println!("path:         {}", entry.path.display());
+            println!(
+                "age:          {}",
+                format_age(&entry.created_at, &system_now)?
+            );
+            println!();
+            println!("{}", envelope.payload);
+        }
+
+        CacheAction::Clear { .. } => {
+            let cutoff = action
+                .try_parse_older_than()?
+                .map(|older_than| resolve_time_bound(zoned_now, &older_than))
+                .transpose()?;
+
+            let mut removed = 0;
+
+            for entry in io::cache::try_list_entries()? {
+                if cutoff
+                    .as_ref()
+                    .is_none_or(|cutoff| entry.created_at < cutoff.timestamp())
+                {
+                    io::cache::try_remove_entry(&entry)?;
+                    removed += 1;
+                }
+            }
+
+            // The stores too, they keep closed buckets forever otherwise.
+            // One that is being fetched into right now is left alone.
+            let mut removed_stores = 0;
+            let mut stores_in_use = 0;
+
+            for store in io::bucket_store::try_list_stores()? {
+                if cutoff
+                    .as_ref()
+                    .is_some_and(|cutoff| store.modified_at >= cutoff.timestamp())
+                {
+                    continue;
+                }
+
+                if io::bucket_store::try_remove_store(&store)? {
+                    removed_stores += 1;
+                } else {
+                    stores_in_use += 1;
+                }
+            }
+
+            io::cache::try_remove_orphan_locks(&io::cache::cache_dir()?)?;
+            io::cache::try_remove_orphan_locks(&io::bucket_store::store_dir()?)?;
+
+            println!("Removed {removed} cached results and {removed_stores} usage stores.");
+
+            if stores_in_use > 0 {
+                println!("Skipped {stores_in_use} usage stores, still being fetched into.");
+            }
+        }
+
+        CacheAction::Path => println!("{}", io::cache::meter_cache_dir()?.display()),
+    }
+
+    Ok(())
+}
+
+/// How long ago, in the largest units that make sense, e.g. "2h 5m".
+fn format_age(then: &Timestamp, now: &Timestamp) -> AppResult<String> {
+    let age = now
+        .since(*then)
+        .into_diagnostic()?
+        .round(
+            jiff::SpanRound::new()
+                .largest(Unit::Hour)
+                .smallest(Unit::Second),
+        )
+        .into_diagnostic()?;
+
+    Ok(format!("{age:#}"))
+}
+
+fn format_size(size_in_bytes: u64) -> String {
+    match size_in_bytes {
+        0..1024 => format!("{size_in_bytes} B"),
+        _ => format!("{:.1} KiB", size_in_bytes as f64 / 1024.0),
+    }
+}
+
+/// The command line as it was typed, for `meter cache list`.
+/// The keys are left out, so are the flags that don't change the result.
+fn describe_command() -> String {
+    const SECRET_FLAGS: [&str; 2] = ["--anthropic-admin-api-key", "--openai-admin-api-key"];
+    const COSMETIC_FLAGS: [&str; 2] = ["--no-animate", "--stale-ok"];
+
+    let mut words = vec!["meter".to_owned()];
+    let mut is_secret_next = false;
+
+    for arg in std::env::args().skip(1) {
+        if is_secret_next {
+            words.push("***".to_owned());
+            is_secret_next = false;
+        } else if let Some(flag) = SECRET_FLAGS.iter().find(|flag| arg.starts_with(*flag)) {
+            is_secret_next = arg == *flag;
+            words.push(if is_secret_next {
+                arg
+            } else {
+                format!("{flag}=***")
+            });
+        } else if !COSMETIC_FLAGS.contains(&arg.as_str()) {
+            words.push(arg);
+        }
+    }
+
+    words.join(" ")
+}
+
+/// The actual application logic, fetch everything, then do the thing.
+fn refresh_report(
+    app: &app::App,
+    sources: &[SourceKeyPair],
+    report_start: &Zoned,
+    report_end: Option<&Zoned>,
+    system_now: &Timestamp,
+) -> AppResult<UsageReport> {
+    app.display.maybe_start_spin();
+
+    // One thread per provider, they don't share anything but the spinner.
+    // Anthropic waits between pages, so the others are done long before it anyway.
+    let results = std::thread::scope(|scope| {
+        let handles = sources
+            .iter()
+            .map(|source_key_pair| {
+                scope.spawn(|| {
+                    try_fetch_unified(app, report_start, report_end, source_key_pair, system_now)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("A fetching thread panicked."))
+            .collect::<Vec<_>>()
+    });
+
+    // Every store that made it is already saved by now, whatever happened to the others.
+    let results = sources
+        .iter()
+        .map(|(source, _)| source.provider())
+        .zip(results)
+        .collect();
+
+    router::does_the_thing(app, results)
+}
+
+/// Gets the unified buckets of a provider for the report range.
+///
+/// Goes through the bucket store, which only asks the API for what it doesn't have yet,
+/// usually the last incomplete bucket. The rest is read back from disk.
+///
+/// Unlike the output cache, the store doesn't care about the command, so `sum` and `raw`
+/// over the same range share the same data.
 fn try_fetch_unified(
     ctx: &app::App,
     report_start: &Zoned,
-    provider: &Provider,
+    report_end: Option<&Zoned>,
+    (source, key): &SourceKeyPair,
+    system_now: &Timestamp,
 ) -> AppResult<Vec<UnifiedBucketByTime>> {
-    match *provider {
-        Provider::Anthropic => {
-            let anthropic_usages: Vec<BucketByTime> =
-                crate::io::claude_client::fetch(ctx, report_start, None)?;
-            let unified_usages =
-                crate::calculation::transformation::unify_from_anthropic(anthropic_usages)?;
-
-            Ok(unified_usages)
+    let now = system_now.as_second();
+    let start = report_start.timestamp().as_second();
+    let end = report_end.map(|end| end.timestamp().as_second());
+
+    let store_file_path = io::bucket_store::create_bucket_store_file_path(
+        &source.provider(),
+        key,
+        source.base_url(&ctx.cli),
+    )?;
+
+    // Other commands share the same store, `sum` and `raw` for example.
+    // Let one of them fetch, the others will find the buckets already there.
+    let _lock = io::cache::try_acquire_lock(&store_file_path, LOCK_TIMEOUT)?.ok_or_else(|| {
+        Error::StoreLockTimeout {
+            provider: source.provider().to_string(),
         }
+    })?;
 
-        _ => unimplemented!("Do it already!"),
+    let mut store = io::bucket_store::try_load(&store_file_path)?;
+
+    let fetch_ranges = store.plan(start, end, now, &ctx.cli.ttl_minutes);
+
+    if fetch_ranges.is_empty() {
+        return Ok(store.select(start, end));
     }
-}
 
-/// Hashes serialized arguments into a cache filename.
-///
-/// Uses XxHash64 to produce a fast, deterministic hash of the input,
-/// then formats it as hexadecimal.
-///
-/// Returns a short string like "7a2f4c91b0e3".
-fn generate_cache_filename(serialized_args: &str) -> String {
-    let mut hasher = XxHash64::default();
+    for fetch_range @ (fetch_start, fetch_end) in fetch_ranges {
+        let to_zoned = |second: i64| -> AppResult<Zoned> {
+            let timestamp = Timestamp::from_second(second).into_diagnostic()?;
+
+            Ok(timestamp.to_zoned(ctx.time_zone.clone()))
+        };
+
+        let fetch_start = to_zoned(fetch_start)?;
+        let fetch_end = fetch_end.map(to_zoned).transpose()?;
+
+        let unified_usages = source.fetch_unified(ctx, key, &fetch_start, fetch_end.as_ref())?;
 
-    hasher.write(serialized_args.as_bytes());
-    let hashed = hasher.finish();
+        store.merge(fetch_range, unified_usages, now);
+    }
+
+    io::bucket_store::try_save(&store_file_path, &store)?;
 
-    // Returns something like "7a2f4c91b0e3"
-    format!("{:x}", hashed)
+    Ok(store.select(start, end))
 }
 
 /// Generates a cache key from CLI arguments.
 ///
 /// Serializes the provided CLI args to JSON and produces a cache filename
 /// that uniquely identifies the command.
-fn create_args_signature(cli: &Cli) -> AppResult<String> {
-    let serialized = serde_json::to_string(cli)
+///
+/// The resolved report range goes in too. The args alone would say "this-month" forever,
+/// and the cached answer has to expire when the month rolls over.
+/// So does the timezone, since it moves every midnight, even when --tz comes from the system.
+fn create_args_signature(
+    cli: &Cli,
+    report_start: &Zoned,
+    report_end: Option<&Zoned>,
+) -> AppResult<String> {
+    let signature = serde_json::json!({
+        "cli": cli,
+        "report_start": report_start.timestamp().to_string(),
+        "report_end": report_end.map(|end| end.timestamp().to_string()),
+        "time_zone": report_start
+            .time_zone()
+            .iana_name()
+            .map(str::to_owned)
+            .unwrap_or_else(|| report_start.offset().to_string()),
+    });
+
+    let serialized = serde_json::to_string(&signature)
         .into_diagnostic()
         .wrap_err("Failed to serialize command arguments; debounce failed, operation rejected.")?;
 
-    let file_name = generate_cache_filename(&serialized);
+    let file_name = io::cache::generate_cache_filename(&serialized);
 
     Ok(file_name)
 }
 
-/// Calculates the start of a day N days ago.
+/// Works out where the report starts and ends (exclusive), `None` meaning now.
+///
+/// A named period wins, otherwise it's --since and --until.
+fn resolve_report_range(cli: &Cli, zoned_now: &Zoned) -> AppResult<(Zoned, Option<Zoned>)> {
+    let (report_start, report_end) = match &cli.period {
+        Some(period) => resolve_period(zoned_now, period)?,
+        None => {
+            let report_start = resolve_time_bound(zoned_now, &cli.try_parse_since()?)?;
+            let report_end = cli
+                .try_parse_until()?
+                .map(|until| resolve_time_bound(zoned_now, &until))
+                .transpose()?;
+
+            (report_start, report_end)
+        }
+    };
+
+    ensure_time_range(&report_start, report_end.as_ref())?;
+
+    Ok((report_start, report_end))
+}
+
+/// Turns a named calendar period into a range, in the timezone of now.
+///
+/// Periods that are still going on end now, the closed ones end at midnight
+/// of the day after them.
+fn resolve_period(zoned_now: &Zoned, period: &Period) -> AppResult<(Zoned, Option<Zoned>)> {
+    let today = zoned_now.start_of_day().into_diagnostic()?;
+
+    let this_week = {
+        let days_since_monday = today.weekday().to_monday_zero_offset();
+
+        today
+            .checked_sub(Span::new().days(days_since_monday))
+            .into_diagnostic()?
+            .start_of_day()
+            .into_diagnostic()?
+    };
+
+    let this_month = today
+        .first_of_month()
+        .into_diagnostic()?
+        .start_of_day()
+        .into_diagnostic()?;
+
+    let range = match period {
+        Period::Today => (today, None),
+        Period::Yesterday => {
+            let yesterday = today
+                .checked_sub(Span::new().days(1))
+                .into_diagnostic()?
+                .start_of_day()
+                .into_diagnostic()?;
+
+            (yesterday, Some(today))
+        }
+        Period::ThisWeek => (this_week, None),
+        Period::LastWeek => {
+            let last_week = this_week
+                .checked_sub(Span::new().weeks(1))
+                .into_diagnostic()?
+                .start_of_day()
+                .into_diagnostic()?;
+
+            (last_week, Some(this_week))
+        }
+        Period::ThisMonth | Period::Mtd => (this_month, None),
+        Period::LastMonth => {
+            let last_month = this_month
+                .checked_sub(Span::new().months(1))
+                .into_diagnostic()?
+                .start_of_day()
+                .into_diagnostic()?;
+
+            (last_month, Some(this_month))
+        }
+        Period::Ytd => {
+            let this_year = today
+                .first_of_year()
+                .into_diagnostic()?
+                .start_of_day()
+                .into_diagnostic()?;
+
+            (this_year, None)
+        }
+    };
+
+    Ok(range)
+}
+
+/// Turns a time bound into a real time, relative to now.
 ///
-/// Subtracts `days_ago` from the given time, then returns midnight
-/// of that resulting date in the same timezone.
-fn calculate_start_date(zoned_now: &Zoned, days_ago: i64) -> AppResult<Zoned> {
-    let time_span = Span::new().days(days_ago);
+/// Days, weeks and months go back that many calendar units from now, then snap to midnight
+/// of the resulting date in the same timezone. Hours snap to the start of the hour instead.
+/// Dates are midnight in the same timezone, and timestamps are taken as they are.
+fn resolve_time_bound(zoned_now: &Zoned, time_bound: &TimeBound) -> AppResult<Zoned> {
+    // The parser takes any u32, jiff spans don't. Way too far back is still just a bad duration.
+    let out_of_range = |amount: i64, unit: &str| Error::InvalidDuration(format!("{amount}{unit}"));
+
+    let time_span = match *time_bound {
+        TimeBound::HoursAgo(hours) => {
+            let time_span = Span::new()
+                .try_hours(hours)
+                .map_err(|_| out_of_range(hours, "h"))?;
+            let target_time = zoned_now.checked_sub(time_span).into_diagnostic()?;
+
+            let target_start_of_hour = target_time
+                .round(
+                    ZonedRound::new()
+                        .smallest(Unit::Hour)
+                        .mode(RoundMode::Trunc),
+                )
+                .into_diagnostic()?;
+
+            return Ok(target_start_of_hour);
+        }
+        TimeBound::DaysAgo(days) => Span::new()
+            .try_days(days)
+            .map_err(|_| out_of_range(days, "d"))?,
+        TimeBound::WeeksAgo(weeks) => Span::new()
+            .try_weeks(weeks)
+            .map_err(|_| out_of_range(weeks, "w"))?,
+        TimeBound::MonthsAgo(months) => Span::new()
+            .try_months(months)
+            .map_err(|_| out_of_range(months, "mo"))?,
+        TimeBound::Date(date) => {
+            return date
+                .to_zoned(zoned_now.time_zone().clone())
+                .into_diagnostic()
+                .wrap_err(
+                    "Could not resolve the start of the day (midnight) for this date/timezone",
+                );
+        }
+        TimeBound::Timestamp(timestamp) => {
+            return Ok(timestamp.to_zoned(zoned_now.time_zone().clone()));
+        }
+    };
 
     let target_date = zoned_now.checked_sub(time_span).into_diagnostic()?;
 
@@ -162,18 +698,14 @@ fn calculate_start_date(zoned_now: &Zoned, days_ago: i64) -> AppResult<Zoned> {
     Ok(target_start_of_day)
 }
 
-/// Compose the platform-specific cache file path for a given argument signature.
-///
-/// The resulting path follows the pattern:
-/// `{cache_dir}/meter/claude/cache_7a2f4c91b0e3`
-fn create_cache_file_path(args_signature: &str) -> AppResult<std::path::PathBuf> {
-    let dir = dirs::cache_dir()
-        .ok_or_else(|| miette!("Could not find a cache directory."))?
-        .join("meter")
-        .join("claude");
-
-    let file_name = format!("cache_{}", args_signature);
-    let file_path = dir.join(file_name);
-
-    Ok(file_path)
+/// Makes sure there is something between the start and the end of the report.
+fn ensure_time_range(report_start: &Zoned, report_end: Option<&Zoned>) -> AppResult<()> {
+    match report_end {
+        Some(end) if end <= report_start => Err(Error::EmptyTimeRange {
+            start: report_start.to_string(),
+            end: end.to_string(),
+        }
+        .into()),
+        _ => Ok(()),
+    }
 }
@@ -1,45 +1,42 @@
 use crate::calculation::unified::{collapse_cost, collapse_tokens, fold, make_primitives};
 use crate::calculation::usage_report::UsageReport;
-use crate::cli::{Commands, Grouping, Metric, SumArgs};
+use crate::cli::{Commands, Metric, Provider, SumArgs, UnknownModelPolicy};
 
 use crate::app::App;
+use crate::error::Error;
 use crate::io::unified_dtos::UnifiedBucketByTime;
 use crate::prelude::*;
+use itertools::Itertools;
+
+/// What fetching one provider came to.
+pub type SourceResult = (Provider, AppResult<Vec<UnifiedBucketByTime>>);
 
 /// We will see...
-pub fn does_the_thing(
-    ctx: &App,
-    unified_usages: Vec<UnifiedBucketByTime>,
-) -> AppResult<UsageReport> {
-    let primitive_form = make_primitives(unified_usages.clone())?;
+pub fn does_the_thing(ctx: &App, results: Vec<SourceResult>) -> AppResult<UsageReport> {
+    let total = results.len();
+    let mut unified_usages = vec![];
+    let mut failures = vec![];
+
+    for (provider, result) in results {
+        match result {
+            Ok(usages) => unified_usages.extend(usages),
+            Err(e) => failures.push((provider, e)),
+        }
+    }
+
+    // Without --partial, a missing provider is an error, a smaller bill would be a lie.
+    // With it, there still has to be something left to show.
+    if !failures.is_empty() && (!ctx.cli.partial || failures.len() == total) {
+        bail!(combine_fetch_errors(total, failures));
+    }
 
     let output: UsageReport = match &ctx.cli.command {
         // meter sum.
-        Commands::Sum(args) => match args {
-            SumArgs {
-                metric: Metric::Tokens,
-                group_by: Some(Grouping::Model),
-            } => collapse_tokens(primitive_form).into(),
-
-            SumArgs {
-                metric: Metric::Tokens,
-                group_by: None,
-            } => fold(collapse_tokens(primitive_form)).into(),
-
-            SumArgs {
-                metric: Metric::Cost,
-                group_by: Some(Grouping::Model),
-            } => collapse_cost(primitive_form).into(),
-
-            SumArgs {
-                metric: Metric::Cost,
-                group_by: None,
-                ..
-            } => fold(collapse_cost(primitive_form)).into(),
-        },
+        Commands::Sum(args) => sum(ctx, args, unified_usages)?,
 
         // meter raw.
         Commands::Raw => {
+            // No prices involved, an unknown model is none of its business.
             let json = if ctx.cli.unformatted {
                 serde_json::to_string(&unified_usages).into_diagnostic()?
             } else {
@@ -48,7 +45,99 @@ pub fn does_the_thing(
 
             UsageReport::Raw(json)
         }
+
+        // meter cache, handled in main before anything is fetched.
+        Commands::Cache(_) => unreachable!(),
+    };
+
+    if failures.is_empty() {
+        return Ok(output);
+    }
+
+    let (missing, errors) = failures
+        .into_iter()
+        .map(|(provider, e)| (provider.to_string(), e))
+        .unzip();
+
+    Ok(UsageReport::Partial {
+        report: Box::new(output),
+        missing,
+        errors,
+    })
+}
+
+/// Adds up the usage the way `meter sum` was asked to.
+fn sum(
+    ctx: &App,
+    args: &SumArgs,
+    unified_usages: Vec<UnifiedBucketByTime>,
+) -> AppResult<UsageReport> {
+    let SumArgs {
+        metric,
+        group_by,
+        on_unknown_model,
+    } = args;
+
+    // Tokens don't need a price, an unknown model is only a problem for money.
+    // Not even a fallback, it could be missing from the table too. Only skip still means skip.
+    let on_unknown_model = match (metric, on_unknown_model) {
+        (Metric::Cost, _) | (Metric::Tokens, UnknownModelPolicy::Skip) => on_unknown_model,
+        (Metric::Tokens, _) => &UnknownModelPolicy::Zero,
+    };
+
+    // Totals don't care about the key, so they don't group at all.
+    let groupings = group_by.as_deref().unwrap_or_default();
+
+    let (primitive_form, unpriced) = make_primitives(
+        unified_usages,
+        &ctx.pricing,
+        groupings,
+        on_unknown_model,
+        &ctx.time_zone,
+    )?;
+
+    let report: UsageReport = match (metric, group_by) {
+        (Metric::Tokens, Some(_)) => collapse_tokens(primitive_form).into(),
+        (Metric::Tokens, None) => fold(collapse_tokens(primitive_form)).into(),
+        (Metric::Cost, Some(_)) => collapse_cost(primitive_form).into(),
+        (Metric::Cost, None) => fold(collapse_cost(primitive_form)).into(),
+    };
+
+    // The number is short by whatever those models used, say so right under it.
+    let is_short = matches!(metric, Metric::Cost) || *on_unknown_model == UnknownModelPolicy::Skip;
+
+    if unpriced.is_empty() || !is_short {
+        return Ok(report);
+    }
+
+    Ok(UsageReport::Unpriced {
+        report: Box::new(report),
+        models: unpriced.into_iter().collect(),
+    })
+}
+
+/// A lone provider's error is shown as it is, several are listed under the ones that failed.
+fn combine_fetch_errors(
+    total: usize,
+    mut failures: Vec<(Provider, miette::Report)>,
+) -> miette::Report {
+    if total == 1 && failures.len() == 1 {
+        return failures.remove(0).1;
+    }
+
+    let failed = failures.iter().map(|(provider, _)| provider).join(", ");
+    let advice = if failures.len() < total {
+        "The other providers were fetched and saved, the next run only asks for what's missing.\n\
+Or add --partial to see what they add up to."
+    } else {
+        "None of them made it, see why below."
     };
+    let errors = failures.into_iter().map(|(_, e)| e.into()).collect();
 
-    Ok(output)
+    Error::SourcesFailed {
+        failed,
+        advice: advice.to_owned(),
+        errors,
+    }
+    .into()
 }